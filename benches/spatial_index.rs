@@ -0,0 +1,119 @@
+// hit-testing the mouse position against every segment, as the editor did, versus asking the spatial index first
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use osm_parser::{Coordinate, Id, Node, OsmData, Tags, Way};
+
+#[allow(dead_code)]
+#[path = "../src/app/spatial.rs"]
+mod spatial;
+
+use spatial::{Item, Point, SpatialIndex};
+
+// streets in a grid, every street is split into ways of ten segments like in real data
+const GRID: i64 = 200;
+const SPACING: f64 = 0.0005;
+const WAY_LENGTH: i64 = 10;
+// roughly the width of a road at zoom 17
+const TOLERANCE: f64 = 0.00003;
+
+fn grid() -> OsmData {
+	let mut data = OsmData::default();
+	let id = |x: i64, y: i64| y * GRID + x + 1;
+
+	for y in 0..GRID {
+		for x in 0..GRID {
+			let pos = Coordinate { lat: 50.0 + y as f64 * SPACING, lon: 10.0 + x as f64 * SPACING };
+			data.nodes.insert(id(x, y), Node { id: id(x, y), pos, tags: Tags::new() });
+		}
+	}
+
+	let mut next = GRID * GRID + 1;
+	let mut street = |data: &mut OsmData, nodes: Vec<Id>| {
+		for chunk in nodes.windows(WAY_LENGTH as usize + 1).step_by(WAY_LENGTH as usize) {
+			data.ways.insert(next, Way { id: next, nodes: chunk.to_vec(), tags: Tags::from([("highway".to_string(), "residential".to_string())]) });
+			next += 1;
+		}
+	};
+	for i in 0..GRID {
+		street(&mut data, (0..GRID).map(|x| id(x, i)).collect());
+		street(&mut data, (0..GRID).map(|y| id(i, y)).collect());
+	}
+
+	data
+}
+
+// spread over the grid, some on streets and some between them
+fn mouse_positions() -> Vec<Point> {
+	(0..20).map(|i| {
+		let t = i as f64 / 20.0;
+		[10.0 + t * (GRID - 1) as f64 * SPACING, 50.0 + (t * 7.3).fract() * (GRID - 1) as f64 * SPACING]
+	}).collect()
+}
+
+fn point(data: &OsmData, id: &Id) -> Point {
+	let pos = &data.nodes[id].pos;
+	[pos.lon, pos.lat]
+}
+
+fn distance_to_segment(p: Point, a: Point, b: Point) -> f64 {
+	let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+	let len_sq = dx * dx + dy * dy;
+	let t = if len_sq == 0.0 { 0.0 } else { (((p[0] - a[0]) * dx + (p[1] - a[1]) * dy) / len_sq).clamp(0.0, 1.0) };
+	((p[0] - a[0] - t * dx).powi(2) + (p[1] - a[1] - t * dy).powi(2)).sqrt()
+}
+
+fn linear_scan(data: &OsmData, p: Point) -> Option<Id> {
+	let mut closest = None;
+	for way in data.ways.values() {
+		for s in way.nodes.windows(2) {
+			let distance = distance_to_segment(p, point(data, &s[0]), point(data, &s[1]));
+			if distance < TOLERANCE && closest.is_none_or(|(_, d)| distance < d) {
+				closest = Some((way.id, distance));
+			}
+		}
+	}
+	closest.map(|(id, _)| id)
+}
+
+fn indexed(index: &SpatialIndex, data: &OsmData, p: Point) -> Option<Id> {
+	let mut closest = None;
+	for item in index.locate([p[0] - TOLERANCE, p[1] - TOLERANCE], [p[0] + TOLERANCE, p[1] + TOLERANCE]) {
+		if let Item::Segment(id, i) = item {
+			let nodes = &data.ways[&id].nodes;
+			let distance = distance_to_segment(p, point(data, &nodes[i]), point(data, &nodes[i + 1]));
+			if distance < TOLERANCE && closest.is_none_or(|(_, d)| distance < d) {
+				closest = Some((id, distance));
+			}
+		}
+	}
+	closest.map(|(id, _)| id)
+}
+
+fn hit_testing(c: &mut Criterion) {
+	let data = grid();
+	let index = SpatialIndex::new(&data);
+	let positions = mouse_positions();
+
+	// both have to agree before their speed means anything
+	for p in &positions {
+		let (a, b) = (linear_scan(&data, *p), indexed(&index, &data, *p));
+		assert_eq!(a.is_some(), b.is_some());
+	}
+
+	let mut group = c.benchmark_group("hover");
+	group.bench_function("linear scan", |b| b.iter(|| {
+		positions.iter().map(|p| linear_scan(&data, black_box(*p))).filter(Option::is_some).count()
+	}));
+	group.bench_function("spatial index", |b| b.iter(|| {
+		positions.iter().map(|p| indexed(&index, &data, black_box(*p))).filter(Option::is_some).count()
+	}));
+	group.finish();
+
+	c.bench_function("build index", |b| b.iter(|| SpatialIndex::new(black_box(&data))));
+}
+
+criterion_group! {
+	name = benches;
+	config = Criterion::default().sample_size(10);
+	targets = hit_testing
+}
+criterion_main!(benches);
@@ -1,15 +1,30 @@
-mod places;
 mod windows;
 mod editor;
 mod providers;
+mod document;
+mod history;
+mod io;
+mod file_browser;
+mod view;
+mod bookmarks;
+mod spatial;
 
+use bookmarks::Bookmarks;
+use document::{Bounds, Document, ElementRef};
 use editor::visual::Visualization;
+use editor::detail::LevelOfDetail;
+use editor::style::StyleSheet;
 use editor::EditorPluginState;
 use eframe::egui;
-use egui::{Context, Frame};
-use osm_parser::OsmData;
+use eframe::CreationContext;
+use egui::{Context, Frame, Key, KeyboardShortcut, Modifiers};
+use file_browser::FileBrowser;
+use history::{Command, History};
 use providers::Provider;
+use windows::{MenuAction, ZoomTarget};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use editor::coordinate_to_pos;
 use walkers::{Map, MapMemory, Tiles};
 
 pub struct MyApp {
@@ -17,27 +32,174 @@ pub struct MyApp {
 	selected_provider: Provider,
 	selected_visualizer: Visualization,
 	map_memory: MapMemory,
-	osm_data: OsmData,
+	document: Document,
+	history: History,
 	scale_factor: f32,
+	detail: LevelOfDetail,
+	style: StyleSheet,
+	// result of the last file operation
+	status: Option<String>,
+	error: Option<String>,
+	file_browser: FileBrowser,
+	// fit the view to the data once the map size is known
+	fit_to_data: bool,
+	// area currently shown, used to clip large files
+	view_bounds: Option<Bounds>,
+	bookmarks: Bookmarks,
 	editor_state: EditorPluginState,
 }
 
 impl MyApp {
-	pub fn new(egui_ctx: Context) -> Self {
-		Self {
-			providers: providers::providers(egui_ctx),
+	pub fn new(cc: &CreationContext, path: Option<PathBuf>) -> Self {
+		let mut app = Self {
+			providers: providers::providers(cc.egui_ctx.clone()),
 			selected_provider: Default::default(),
 			selected_visualizer: Default::default(),
 			map_memory: Default::default(),
-			osm_data: osm_parser::parse("school.osm").unwrap(),
+			document: Default::default(),
+			history: Default::default(),
 			scale_factor: 1.0,
+			detail: Default::default(),
+			style: Default::default(),
+			status: None,
+			error: None,
+			file_browser: Default::default(),
+			fit_to_data: false,
+			view_bounds: None,
+			bookmarks: cc.storage
+				.and_then(|storage| eframe::get_value(storage, bookmarks::STORAGE_KEY))
+				.unwrap_or_default(),
 			editor_state: Default::default(),
+		};
+
+		if let Some(path) = path {
+			app.open(&path, None);
+		}
+
+		app
+	}
+
+	// replaces the current document, edits and selection refer to the old one,
+	// the file browser asks before unsaved changes are thrown away
+	fn open(&mut self, path: &Path, clip: Option<Bounds>) {
+		match io::load(path, clip) {
+			Ok(document) => {
+				self.document = document;
+				self.fit_to_data = true;
+				self.history = Default::default();
+				self.editor_state = Default::default();
+				self.status = Some(format!("Opened {}", path.display()));
+			},
+			Err(e) => self.error = Some(format!("Could not open {}: {e}", path.display())),
+		}
+	}
+
+	fn open_style(&mut self, path: &Path) {
+		match StyleSheet::load(path) {
+			Ok(style) => {
+				self.style = style;
+				self.status = Some(format!("Loaded style {}", path.display()));
+			},
+			Err(e) => self.error = Some(format!("Could not load style {}: {e}", path.display())),
+		}
+	}
+
+	fn delete_selected(&mut self) {
+		let command = match self.editor_state.selected {
+			Some(ElementRef::Way(id)) => Command::DeleteWay(id),
+			Some(ElementRef::Node(id)) => Command::DeleteNode(id),
+			Some(ElementRef::Relation(id)) => Command::DeleteRelation(id),
+			None => return,
+		};
+
+		self.history.execute(&mut self.document, command);
+		self.editor_state.selected = None;
+		self.editor_state.edit_window_pos = None;
+	}
+
+	fn save(&mut self, action: MenuAction, path: &Path) {
+		let result = match action {
+			MenuAction::SaveJson => io::json::save(&self.document, path),
+			MenuAction::SaveXml => io::xml::save(&self.document, path),
+			MenuAction::ExportChanges => io::osc::save(&self.document, path),
+			MenuAction::Open | MenuAction::OpenStyle | MenuAction::Bookmarks => return,
+		};
+
+		let saved = result.is_ok();
+		match result {
+			Ok(()) => self.status = Some(format!("Saved {}", path.display())),
+			Err(e) => self.error = Some(format!("Could not save {}: {e}", path.display())),
+		}
+
+		// OSM JSON has no change markers, the edits are saved but can no longer be exported as changes
+		let changes = self.document.change_count();
+		if matches!(action, MenuAction::SaveJson) && saved && changes > 0 {
+			self.error = Some(format!(
+				"{} does not record which objects were created, modified or deleted, \
+				so its {changes} local changes will be missing from an osmChange export once it is opened again. \
+				Save as OSM XML to keep them.",
+				path.display(),
+			));
 		}
 	}
 }
 
 impl eframe::App for MyApp {
+	fn save(&mut self, storage: &mut dyn eframe::Storage) {
+		eframe::set_value(storage, bookmarks::STORAGE_KEY, &self.bookmarks);
+	}
+
 	fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
+		// text fields handle their own undo
+		if !ctx.wants_keyboard_input() {
+			let redo = KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z);
+			let undo = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+
+			if ctx.input_mut(|i| i.consume_shortcut(&redo)) {
+				self.history.redo(&mut self.document);
+			} else if ctx.input_mut(|i| i.consume_shortcut(&undo)) {
+				self.history.undo(&mut self.document);
+			} else if ctx.input(|i| i.key_pressed(Key::Delete)) {
+				self.delete_selected();
+			}
+		}
+
+		match windows::menu_bar(ctx, self.status.as_deref()) {
+			Some(action @ MenuAction::Open) => self.file_browser.open(action, ""),
+			Some(action @ MenuAction::SaveJson) => self.file_browser.open(action, "edited.json"),
+			Some(action @ MenuAction::SaveXml) => self.file_browser.open(action, "edited.osm"),
+			Some(action @ MenuAction::ExportChanges) => self.file_browser.open(action, "changes.osc"),
+			Some(action @ MenuAction::OpenStyle) => self.file_browser.open(action, ""),
+			Some(MenuAction::Bookmarks) => self.bookmarks.open = !self.bookmarks.open,
+			None => {},
+		}
+
+		match self.file_browser.show(ctx, self.document.change_count()) {
+			Some((MenuAction::Open, path)) => {
+				let clip = self.view_bounds.filter(|_| self.file_browser.clip_to_view);
+				self.open(&path, clip);
+			},
+			Some((MenuAction::OpenStyle, path)) => self.open_style(&path),
+			Some((action, path)) => self.save(action, &path),
+			None => {},
+		}
+
+		// a loaded stylesheet is applied again whenever it is saved
+		if let Some(path) = self.style.path().map(Path::to_path_buf) {
+			match self.style.reload() {
+				Some(Ok(())) => self.status = Some(format!("Reloaded style {}", path.display())),
+				Some(Err(e)) => self.error = Some(format!("Could not reload style {}: {e}", path.display())),
+				None => {},
+			}
+			ctx.request_repaint_after(editor::style::STYLE_RELOAD_INTERVAL);
+		}
+
+		windows::error(ctx, &mut self.error);
+
+		if let Some(position) = windows::history(ctx, &self.history) {
+			self.history.jump(&mut self.document, position);
+		}
+
 		egui::CentralPanel::default()
 			.frame(Frame::none())
 			.show(ctx, |ui| {
@@ -48,21 +210,63 @@ impl eframe::App for MyApp {
 					.as_mut();
 				let attribution = tiles.attribution();
 
-				ui.add(Map::new(Some(tiles), &mut self.map_memory, places::school())
+				if std::mem::take(&mut self.fit_to_data) {
+					if let Some(bounds) = self.document.extent() {
+						view::fit(&mut self.map_memory, bounds, ui.available_size());
+					}
+				}
+
+				// the map falls back to this position until it is moved
+				let home = self.document.extent()
+					.map(|b| coordinate_to_pos(&b.center()))
+					.unwrap_or_else(|| self.bookmarks.home());
+
+				let center = self.map_memory.detached().unwrap_or(home);
+				if let Some(bookmark) = self.bookmarks.show(ctx, center, self.map_memory.zoom()) {
+					self.map_memory.center_at(bookmark.position());
+					let _ = self.map_memory.set_zoom(bookmark.zoom);
+				}
+
+				let map_size = ui.available_size();
+				let zoom = self.map_memory.zoom();
+				self.view_bounds = Some(view::visible_bounds(center, self.map_memory.zoom(), map_size));
+				ui.add(Map::new(Some(tiles), &mut self.map_memory, home)
 					.with_plugin(editor::EditorPlugin {
 						state: &mut self.editor_state,
-						osm_data: &self.osm_data,
+						document: &mut self.document,
+						history: &mut self.history,
 						scale_factor: self.scale_factor,
+						zoom,
+						detail: self.detail,
+						style: &self.style,
 						visualization: self.selected_visualizer,
 					})
 				);
 
-				windows::zoom(ui, &mut self.map_memory);
-				windows::controls(ui, &mut self.selected_provider, &mut self.providers.keys(), &mut self.selected_visualizer, &mut self.scale_factor);
+				let target = match windows::zoom(ui, &mut self.map_memory, self.editor_state.selected.is_some()) {
+					Some(ZoomTarget::Data) => self.document.extent(),
+					Some(ZoomTarget::Selection) => self.editor_state.selected.and_then(|e| self.document.element_bounds(e)),
+					None => None,
+				};
+				if let Some(bounds) = target {
+					view::fit(&mut self.map_memory, bounds, map_size);
+				}
+				windows::controls(ui, &mut self.selected_provider, &mut self.providers.keys(), &mut self.selected_visualizer, &mut self.scale_factor, &mut self.detail, self.document.change_count());
 				windows::acknowledge(ui, attribution);
 
-				if let Some(id) = self.editor_state.selected.or(self.editor_state.hovered) {
-					windows::tags(ui, &self.osm_data.ways[&id].tags);
+				if let Some(element) = self.editor_state.selected.or(self.editor_state.hovered) {
+					let members = match element {
+						ElementRef::Relation(id) => self.document.relations()[&id].members.as_slice(),
+						_ => &[],
+					};
+					let parents = self.document.parents(element);
+					if let Some(tags) = self.document.tags(element) {
+						let select = windows::tags(ui, tags, members, &parents, self.document.meta(element), self.document.change(element));
+						if select.is_some_and(|e| self.document.contains(e)) {
+							self.editor_state.selected = select;
+							self.editor_state.edit_window_pos = None;
+						}
+					}
 				}
 			});
 	}
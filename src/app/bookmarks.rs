@@ -0,0 +1,124 @@
+use eframe::egui::{Button, Context, Grid, TextEdit, Window};
+use serde::{Deserialize, Serialize};
+use walkers::Position;
+
+pub const STORAGE_KEY: &str = "bookmarks";
+
+// the school, shown while there is neither data nor a bookmark
+const HOME_LON: f64 = 10.216837;
+const HOME_LAT: f64 = 50.059561;
+
+// saved map views, persisted in eframe's storage
+#[derive(Serialize, Deserialize)]
+pub struct Bookmarks {
+	list: Vec<Bookmark>,
+	#[serde(skip)]
+	pub open: bool,
+	#[serde(skip)]
+	new_name: String,
+	// index and name buffer of the bookmark being renamed
+	#[serde(skip)]
+	renaming: Option<(usize, String)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+	pub name: String,
+	pub lon: f64,
+	pub lat: f64,
+	pub zoom: f64,
+}
+
+impl Default for Bookmarks {
+	fn default() -> Self {
+		Self {
+			list: vec![Bookmark {
+				name: "School".to_string(),
+				lon: HOME_LON,
+				lat: HOME_LAT,
+				zoom: 16.0,
+			}],
+			open: false,
+			new_name: String::new(),
+			renaming: None,
+		}
+	}
+}
+
+impl Bookmark {
+	pub fn position(&self) -> Position {
+		Position::from_lon_lat(self.lon, self.lat)
+	}
+}
+
+impl Bookmarks {
+	// position used while no data is loaded
+	pub fn home(&self) -> Position {
+		self.list.first().map_or(Position::from_lon_lat(HOME_LON, HOME_LAT), Bookmark::position)
+	}
+
+	// returns the bookmark to jump to
+	pub fn show(&mut self, ctx: &Context, center: Position, zoom: f64) -> Option<Bookmark> {
+		let mut jump = None;
+		let mut delete = None;
+		let mut open = self.open;
+
+		Window::new("Bookmarks")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				Grid::new("bookmarks").show(ui, |ui| {
+					for (i, bookmark) in self.list.iter_mut().enumerate() {
+						match &mut self.renaming {
+							Some((index, name)) if *index == i => {
+								let response = ui.add(TextEdit::singleline(name).desired_width(120.));
+								if response.lost_focus() || ui.button("✔").clicked() {
+									if !name.trim().is_empty() {
+										bookmark.name = name.trim().to_string();
+									}
+									self.renaming = None;
+								}
+							},
+							_ => {
+								if ui.button(&bookmark.name).on_hover_text("Go to bookmark").clicked() {
+									jump = Some(bookmark.clone());
+								}
+								if ui.button("✏").on_hover_text("Rename").clicked() {
+									self.renaming = Some((i, bookmark.name.clone()));
+								}
+							},
+						}
+
+						if ui.button("🗑").on_hover_text("Delete").clicked() {
+							delete = Some(i);
+						}
+						ui.end_row();
+					}
+				});
+
+				ui.separator();
+				ui.horizontal(|ui| {
+					ui.add(TextEdit::singleline(&mut self.new_name).hint_text("Name").desired_width(120.));
+
+					let name = self.new_name.trim();
+					if ui.add_enabled(!name.is_empty(), Button::new("Save current view")).clicked() {
+						self.list.push(Bookmark {
+							name: name.to_string(),
+							lon: center.lon(),
+							lat: center.lat(),
+							zoom,
+						});
+						self.new_name.clear();
+					}
+				});
+			});
+
+		if let Some(i) = delete {
+			self.list.remove(i);
+			self.renaming = None;
+		}
+
+		self.open = open;
+		jump
+	}
+}
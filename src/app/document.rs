@@ -0,0 +1,544 @@
+use super::spatial::SpatialIndex;
+use osm_parser::{Coordinate, Id, Node, OsmData, Tags, Way};
+use std::collections::{HashMap, HashSet};
+
+// editable copy of the loaded data, the single source of truth for what changed locally
+pub struct Document {
+	data: OsmData,
+	// osm_parser does not read relations
+	relations: HashMap<Id, Relation>,
+	// objects removed from `data`, kept for exporting deletions
+	deleted: OsmData,
+	deleted_relations: HashMap<Id, Relation>,
+	meta: HashMap<ElementRef, Meta>,
+	bounds: Option<Bounds>,
+	changes: HashMap<ElementRef, Change>,
+	// follows `data`, every change to it is marked
+	index: SpatialIndex,
+	// counts changes, for anything derived from the data that is kept between frames
+	revision: u64,
+	// state of every object touched since recording started, before it was touched
+	recording: Option<Vec<Snapshot>>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ElementRef {
+	Node(Id),
+	Way(Id),
+	Relation(Id),
+}
+
+impl ElementRef {
+	pub fn id(&self) -> Id {
+		match self {
+			ElementRef::Node(id) | ElementRef::Way(id) | ElementRef::Relation(id) => *id,
+		}
+	}
+
+	// from the type names used in all OSM formats
+	pub fn from_type(name: &str, id: Id) -> Option<Self> {
+		match name {
+			"node" => Some(ElementRef::Node(id)),
+			"way" => Some(ElementRef::Way(id)),
+			"relation" => Some(ElementRef::Relation(id)),
+			_ => None,
+		}
+	}
+
+	pub fn type_name(&self) -> &'static str {
+		match self {
+			ElementRef::Node(_) => "node",
+			ElementRef::Way(_) => "way",
+			ElementRef::Relation(_) => "relation",
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relation {
+	pub id: Id,
+	pub members: Vec<Member>,
+	pub tags: Tags,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+	pub element: ElementRef,
+	pub role: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Change {
+	Created,
+	Modified,
+	Deleted,
+}
+
+// metadata of an object as it was downloaded, new objects have none
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Meta {
+	pub version: u32,
+	pub changeset: u64,
+	pub user: String,
+	pub uid: u64,
+	pub timestamp: String,
+}
+
+// area the data was downloaded for, from the file header
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bounds {
+	pub min_lat: f64,
+	pub min_lon: f64,
+	pub max_lat: f64,
+	pub max_lon: f64,
+}
+
+impl Bounds {
+	pub fn from_coordinates<'a>(coordinates: impl IntoIterator<Item = &'a Coordinate>) -> Option<Self> {
+		coordinates.into_iter().fold(None, |bounds, c| {
+			let b = bounds.unwrap_or(Bounds { min_lat: c.lat, min_lon: c.lon, max_lat: c.lat, max_lon: c.lon });
+			Some(Bounds {
+				min_lat: b.min_lat.min(c.lat),
+				min_lon: b.min_lon.min(c.lon),
+				max_lat: b.max_lat.max(c.lat),
+				max_lon: b.max_lon.max(c.lon),
+			})
+		})
+	}
+
+	pub fn contains(&self, c: &Coordinate) -> bool {
+		(self.min_lat..=self.max_lat).contains(&c.lat) && (self.min_lon..=self.max_lon).contains(&c.lon)
+	}
+
+	pub fn center(&self) -> Coordinate {
+		Coordinate {
+			lat: (self.min_lat + self.max_lat) / 2.0,
+			lon: (self.min_lon + self.max_lon) / 2.0,
+		}
+	}
+}
+
+// complete state of one object, used to undo and redo edits
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+	element: ElementRef,
+	object: Option<Object>,
+	deleted: Option<Object>,
+	change: Option<Change>,
+}
+
+#[derive(Debug, Clone)]
+enum Object {
+	Node(Node),
+	Way(Way),
+	Relation(Relation),
+}
+
+impl Default for Document {
+	fn default() -> Self {
+		Self::new(OsmData::default(), HashMap::new(), HashMap::new(), None)
+	}
+}
+
+impl Document {
+	pub fn new(data: OsmData, relations: HashMap<Id, Relation>, meta: HashMap<ElementRef, Meta>, bounds: Option<Bounds>) -> Self {
+		Self {
+			index: SpatialIndex::new(&data),
+			revision: 0,
+			data,
+			relations,
+			deleted: OsmData::default(),
+			deleted_relations: HashMap::new(),
+			meta,
+			bounds,
+			changes: HashMap::new(),
+			recording: None,
+		}
+	}
+
+	// edits that were saved to the file, e.g. by JOSM, deleted objects are not in the data
+	pub fn with_changes(mut self, deleted: OsmData, deleted_relations: HashMap<Id, Relation>, changes: HashMap<ElementRef, Change>) -> Self {
+		self.deleted = deleted;
+		self.deleted_relations = deleted_relations;
+		self.changes = changes;
+		self
+	}
+
+	pub fn data(&self) -> &OsmData {
+		&self.data
+	}
+
+	pub fn relations(&self) -> &HashMap<Id, Relation> {
+		&self.relations
+	}
+
+	pub fn index(&self) -> &SpatialIndex {
+		&self.index
+	}
+
+	pub fn revision(&self) -> u64 {
+		self.revision
+	}
+
+	pub fn deleted(&self) -> &OsmData {
+		&self.deleted
+	}
+
+	pub fn deleted_relations(&self) -> &HashMap<Id, Relation> {
+		&self.deleted_relations
+	}
+
+	pub fn contains(&self, element: ElementRef) -> bool {
+		match element {
+			ElementRef::Node(id) => self.data.nodes.contains_key(&id),
+			ElementRef::Way(id) => self.data.ways.contains_key(&id),
+			ElementRef::Relation(id) => self.relations.contains_key(&id),
+		}
+	}
+
+	pub fn tags(&self, element: ElementRef) -> Option<&Tags> {
+		match element {
+			ElementRef::Node(id) => self.data.nodes.get(&id).map(|n| &n.tags),
+			ElementRef::Way(id) => self.data.ways.get(&id).map(|w| &w.tags),
+			ElementRef::Relation(id) => self.relations.get(&id).map(|r| &r.tags),
+		}
+	}
+
+	// relations the element is a member of
+	pub fn parents(&self, element: ElementRef) -> Vec<Id> {
+		let mut ids: Vec<Id> = self.relations.values()
+			.filter(|r| r.members.iter().any(|m| m.element == element))
+			.map(|r| r.id)
+			.collect();
+		ids.sort();
+		ids
+	}
+
+	pub fn meta(&self, element: ElementRef) -> Option<&Meta> {
+		self.meta.get(&element)
+	}
+
+	pub fn bounds(&self) -> Option<Bounds> {
+		self.bounds
+	}
+
+	// area covered by the data, the file header takes precedence over the nodes
+	pub fn extent(&self) -> Option<Bounds> {
+		self.bounds.or_else(|| Bounds::from_coordinates(self.data.nodes.values().map(|n| &n.pos)))
+	}
+
+	// nested relations are not followed since they can contain each other
+	pub fn element_bounds(&self, element: ElementRef) -> Option<Bounds> {
+		let nodes: Vec<Id> = match element {
+			ElementRef::Node(id) => vec![id],
+			ElementRef::Way(id) => self.data.ways.get(&id)?.nodes.clone(),
+			ElementRef::Relation(id) => self.relations.get(&id)?.members.iter()
+				.flat_map(|m| match m.element {
+					ElementRef::Node(id) => vec![id],
+					ElementRef::Way(id) => self.data.ways.get(&id).map(|w| w.nodes.clone()).unwrap_or_default(),
+					ElementRef::Relation(_) => vec![],
+				})
+				.collect(),
+		};
+
+		Bounds::from_coordinates(nodes.iter().filter_map(|n| self.data.nodes.get(n)).map(|n| &n.pos))
+	}
+
+	pub fn change(&self, element: ElementRef) -> Option<Change> {
+		self.changes.get(&element).copied()
+	}
+
+	// sorted so that exports are stable
+	pub fn changes(&self) -> Vec<(ElementRef, Change)> {
+		let mut changes: Vec<_> = self.changes.iter().map(|(e, c)| (*e, *c)).collect();
+		changes.sort();
+		changes
+	}
+
+	pub fn change_count(&self) -> usize {
+		self.changes.len()
+	}
+
+	pub fn set_way_tags(&mut self, id: Id, tags: Tags) {
+		if let Some(way) = self.data.ways.get(&id) {
+			if way.tags != tags {
+				self.touch(ElementRef::Way(id));
+				self.data.ways.get_mut(&id).unwrap().tags = tags;
+				self.mark(ElementRef::Way(id), Change::Modified);
+			}
+		}
+	}
+
+	// there are no drawing tools yet, the export tests need new objects
+	#[cfg(test)]
+	pub fn create_node(&mut self, pos: Coordinate, tags: Tags) -> Id {
+		let id = self.next_id();
+		self.touch(ElementRef::Node(id));
+		self.data.nodes.insert(id, Node { id, pos, tags });
+		self.mark(ElementRef::Node(id), Change::Created);
+		id
+	}
+
+	#[cfg(test)]
+	pub fn create_way(&mut self, nodes: Vec<Id>, tags: Tags) -> Id {
+		let id = self.next_id();
+		self.touch(ElementRef::Way(id));
+		self.data.ways.insert(id, Way { id, nodes, tags });
+		self.mark(ElementRef::Way(id), Change::Created);
+		id
+	}
+
+	// also deletes untagged nodes that are no longer part of any way or relation
+	pub fn delete_way(&mut self, id: Id) {
+		if !self.data.ways.contains_key(&id) { return; }
+		self.touch(ElementRef::Way(id));
+		let way = self.data.ways.remove(&id).unwrap();
+
+		for node in &way.nodes {
+			let orphan = self.data.nodes.get(node).is_some_and(|n| n.tags.is_empty())
+				&& !self.data.ways.values().any(|w| w.nodes.contains(node))
+				&& !self.relations.values().any(|r| r.members.iter().any(|m| m.element == ElementRef::Node(*node)));
+
+			if orphan {
+				self.delete_node(*node);
+			}
+		}
+
+		self.remove_from_relations(ElementRef::Way(id));
+		self.deleted.ways.insert(id, way);
+		self.mark(ElementRef::Way(id), Change::Deleted);
+	}
+
+	// also removes the node from its ways, ways left with less than two nodes and rings without area are deleted
+	pub fn delete_node(&mut self, id: Id) {
+		if !self.data.nodes.contains_key(&id) { return; }
+		self.touch(ElementRef::Node(id));
+		let node = self.data.nodes.remove(&id).unwrap();
+
+		self.remove_from_relations(ElementRef::Node(id));
+		self.deleted.nodes.insert(id, node);
+		self.mark(ElementRef::Node(id), Change::Deleted);
+
+		let ways: Vec<Id> = self.data.ways.values().filter(|w| w.nodes.contains(&id)).map(|w| w.id).collect();
+		for way in ways {
+			self.touch(ElementRef::Way(way));
+			let nodes = &mut self.data.ways.get_mut(&way).unwrap().nodes;
+			let closed = nodes.len() > 1 && nodes.first() == nodes.last();
+
+			nodes.retain(|n| *n != id);
+			nodes.dedup();
+			// removing the first node of a closed way opens it
+			if closed && nodes.len() > 1 && nodes.first() != nodes.last() {
+				nodes.push(nodes[0]);
+			}
+
+			// a ring needs three distinct nodes and the first one repeated
+			let distinct = nodes.iter().collect::<HashSet<_>>().len();
+			let degenerate = nodes.len() < 2 || (closed && (nodes.len() < 4 || distinct < 3));
+
+			if degenerate {
+				self.delete_way(way);
+			} else {
+				self.mark(ElementRef::Way(way), Change::Modified);
+			}
+		}
+	}
+
+	pub fn delete_relation(&mut self, id: Id) {
+		if !self.relations.contains_key(&id) { return; }
+		self.touch(ElementRef::Relation(id));
+		let relation = self.relations.remove(&id).unwrap();
+
+		self.remove_from_relations(ElementRef::Relation(id));
+		self.deleted_relations.insert(id, relation);
+		self.mark(ElementRef::Relation(id), Change::Deleted);
+	}
+
+	// a relation must not refer to deleted objects when it is uploaded, relations left without members are deleted
+	fn remove_from_relations(&mut self, element: ElementRef) {
+		let ids: Vec<Id> = self.relations.values()
+			.filter(|r| r.members.iter().any(|m| m.element == element))
+			.map(|r| r.id)
+			.collect();
+
+		for id in ids {
+			self.touch(ElementRef::Relation(id));
+			let members = &mut self.relations.get_mut(&id).unwrap().members;
+			members.retain(|m| m.element != element);
+
+			if members.is_empty() {
+				self.delete_relation(id);
+			} else {
+				self.mark(ElementRef::Relation(id), Change::Modified);
+			}
+		}
+	}
+
+	// runs `edit` and returns the state of everything it touched before and after
+	pub fn record(&mut self, edit: impl FnOnce(&mut Document)) -> (Vec<Snapshot>, Vec<Snapshot>) {
+		self.recording = Some(Vec::new());
+		edit(self);
+
+		let before = self.recording.take().unwrap_or_default();
+		let after = before.iter().map(|s| self.snapshot(s.element)).collect();
+		(before, after)
+	}
+
+	pub fn restore(&mut self, snapshots: &[Snapshot]) {
+		for s in snapshots {
+			match s.element {
+				ElementRef::Node(id) => {
+					restore_node(&mut self.data, id, &s.object);
+					restore_node(&mut self.deleted, id, &s.deleted);
+				},
+				ElementRef::Way(id) => {
+					restore_way(&mut self.data, id, &s.object);
+					restore_way(&mut self.deleted, id, &s.deleted);
+				},
+				ElementRef::Relation(id) => {
+					restore_relation(&mut self.relations, id, &s.object);
+					restore_relation(&mut self.deleted_relations, id, &s.deleted);
+				},
+			}
+
+			match s.change {
+				Some(change) => self.changes.insert(s.element, change),
+				None => self.changes.remove(&s.element),
+			};
+			self.reindex(s.element);
+		}
+	}
+
+	fn snapshot(&self, element: ElementRef) -> Snapshot {
+		let (object, deleted) = match element {
+			ElementRef::Node(id) => (
+				self.data.nodes.get(&id).cloned().map(Object::Node),
+				self.deleted.nodes.get(&id).cloned().map(Object::Node),
+			),
+			ElementRef::Way(id) => (
+				self.data.ways.get(&id).cloned().map(Object::Way),
+				self.deleted.ways.get(&id).cloned().map(Object::Way),
+			),
+			ElementRef::Relation(id) => (
+				self.relations.get(&id).cloned().map(Object::Relation),
+				self.deleted_relations.get(&id).cloned().map(Object::Relation),
+			),
+		};
+
+		Snapshot { element, object, deleted, change: self.change(element) }
+	}
+
+	// must be called before an object is changed
+	fn touch(&mut self, element: ElementRef) {
+		let recorded = match &self.recording {
+			Some(recording) => recording.iter().any(|s| s.element == element),
+			None => return,
+		};
+
+		if !recorded {
+			let snapshot = self.snapshot(element);
+			self.recording.as_mut().unwrap().push(snapshot);
+		}
+	}
+
+	// new objects use negative ids until they are uploaded, below those of new objects in the file or deleted since
+	#[cfg(test)]
+	fn next_id(&self) -> Id {
+		let ids = self.data.nodes.keys().chain(self.data.ways.keys()).chain(self.relations.keys())
+			.chain(self.deleted.nodes.keys()).chain(self.deleted.ways.keys()).chain(self.deleted_relations.keys());
+		ids.copied().min().unwrap_or(0).min(0) - 1
+	}
+
+	fn reindex(&mut self, element: ElementRef) {
+		self.revision += 1;
+		match element {
+			ElementRef::Node(id) => self.index.update_node(id, &self.data),
+			ElementRef::Way(id) => self.index.update_way(id, &self.data),
+			ElementRef::Relation(_) => {},
+		}
+	}
+
+	fn mark(&mut self, element: ElementRef, change: Change) {
+		self.reindex(element);
+		match (self.changes.get(&element), change) {
+			// deleting an object that was never uploaded leaves nothing to report
+			(Some(Change::Created), Change::Deleted) => {
+				self.changes.remove(&element);
+				match element {
+					ElementRef::Node(id) => self.deleted.nodes.remove(&id).map(|_| ()),
+					ElementRef::Way(id) => self.deleted.ways.remove(&id).map(|_| ()),
+					ElementRef::Relation(id) => self.deleted_relations.remove(&id).map(|_| ()),
+				};
+			},
+			(Some(Change::Created), _) => {},
+			_ => { self.changes.insert(element, change); },
+		}
+	}
+}
+
+fn restore_node(data: &mut OsmData, id: Id, object: &Option<Object>) {
+	match object {
+		Some(Object::Node(node)) => { data.nodes.insert(id, node.clone()); },
+		_ => { data.nodes.remove(&id); },
+	}
+}
+
+fn restore_way(data: &mut OsmData, id: Id, object: &Option<Object>) {
+	match object {
+		Some(Object::Way(way)) => { data.ways.insert(id, way.clone()); },
+		_ => { data.ways.remove(&id); },
+	}
+}
+
+fn restore_relation(relations: &mut HashMap<Id, Relation>, id: Id, object: &Option<Object>) {
+	match object {
+		Some(Object::Relation(relation)) => { relations.insert(id, relation.clone()); },
+		_ => { relations.remove(&id); },
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn delete_vertex() {
+		let mut data = OsmData::default();
+		for id in 1..=4 {
+			data.nodes.insert(id, Node { id, pos: Coordinate { lat: 50.0, lon: 10.0 + id as f64 / 1000.0 }, tags: Tags::new() });
+		}
+		data.ways.insert(10, Way { id: 10, nodes: vec![1, 2, 3, 4, 1], tags: Tags::new() });
+		data.ways.insert(11, Way { id: 11, nodes: vec![2, 3], tags: Tags::new() });
+		let mut document = Document::new(data, HashMap::new(), HashMap::new(), None);
+
+		// the ring stays closed without its first node
+		document.delete_node(1);
+		assert_eq!(document.data().ways[&10].nodes, [2, 3, 4, 2]);
+		assert_eq!(document.change(ElementRef::Way(10)), Some(Change::Modified));
+
+		// a way with a single node left is deleted
+		document.delete_node(2);
+		assert!(!document.contains(ElementRef::Way(11)));
+		assert_eq!(document.change(ElementRef::Way(11)), Some(Change::Deleted));
+
+		// the ring [3, 4, 3] has no area left
+		assert!(!document.contains(ElementRef::Way(10)));
+		assert_eq!(document.change(ElementRef::Way(10)), Some(Change::Deleted));
+	}
+
+	#[test]
+	fn delete_triangle_corner() {
+		let mut data = OsmData::default();
+		for id in 1..=3 {
+			data.nodes.insert(id, Node { id, pos: Coordinate { lat: 50.0, lon: 10.0 + id as f64 / 1000.0 }, tags: Tags::new() });
+		}
+		data.ways.insert(10, Way { id: 10, nodes: vec![1, 2, 3, 1], tags: Tags::new() });
+		let mut document = Document::new(data, HashMap::new(), HashMap::new(), None);
+
+		document.delete_node(2);
+		assert!(!document.contains(ElementRef::Way(10)));
+		assert_eq!(document.change(ElementRef::Way(10)), Some(Change::Deleted));
+		// its other nodes were only part of the triangle
+		assert!(document.data().nodes.is_empty());
+	}
+}
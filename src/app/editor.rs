@@ -1,108 +1,375 @@
 pub mod visual;
 mod consts;
 mod attribute2d;
+mod multipolygon;
+pub mod detail;
+mod projection;
+pub mod style;
+mod order;
+mod label;
+#[cfg(test)]
+mod test_util;
 
+use super::document::{Document, ElementRef};
+use super::history::{Command, History};
+use super::spatial::{Item, SpatialIndex};
 use consts::*;
-use eframe::egui::{Pos2, Response, Shape, Ui};
-use eframe::epaint::PathStroke;
+use consts::osm::POI_RADIUS;
+use detail::LevelOfDetail;
+use label::Labels;
+use eframe::egui::{Pos2, Rect, Response, Ui, Vec2};
+use multipolygon::{MultipolygonCache, Polygon};
+use order::DrawOrder;
 use osm_parser::*;
+use projection::{Projection, ProjectionCache};
+use style::{Style, StyleSheet, Subject};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 use visual::Visualization;
 use walkers::{Plugin, Position, Projector};
 
 // data received every frame
 pub struct EditorPlugin<'a> {
 	pub state: &'a mut EditorPluginState,
-	pub osm_data: &'a OsmData,
+	pub document: &'a mut Document,
+	pub history: &'a mut History,
 	pub visualization: Visualization,
 	pub scale_factor: f32,
+	pub zoom: f64,
+	pub detail: LevelOfDetail,
+	pub style: &'a StyleSheet,
+}
+
+// closed way with area tags or multipolygon, projected for this frame
+struct Area {
+	element: ElementRef,
+	polygons: Vec<Polygon>,
+	style: Style,
+	layer: i32,
 }
 
 // data produced every frame
 #[derive(Default)]
 pub struct EditorPluginState {
-	pub hovered: Option<Id>,
-	pub selected: Option<Id>,
+	pub hovered: Option<ElementRef>,
+	pub selected: Option<ElementRef>,
 	pub edit_window_pos: Option<Pos2>,
+	pub projection: ProjectionCache,
+	multipolygons: MultipolygonCache,
+	surfaces: visual::SurfaceCounts,
 }
 
 impl Plugin for EditorPlugin<'_> {
-	// todo: use Shape::Path to draw lines
-	fn run(self: Box<Self>, ui: &mut Ui, resp: &Response, projector: &Projector) {
+	fn run(mut self: Box<Self>, ui: &mut Ui, resp: &Response, projector: &Projector) {
 		let mut shapes_top = Vec::with_capacity(2);
+		self.scale_factor *= detail::width_scale(self.zoom);
+		let simplify = self.detail.simplifies(self.zoom);
 		self.state.hovered = None;
 
-		for way in self.osm_data.ways.values() {
-			for v in way.nodes.windows(2) {
-				let points = [
-					projector.project(coordinate_to_pos(&self.osm_data.nodes[&v[0]].pos)).to_pos2(),
-					projector.project(coordinate_to_pos(&self.osm_data.nodes[&v[1]].pos)).to_pos2(),
-				];
-
-				let width = visual::determine_width_default(way) * self.scale_factor;
-				let color = visual::determine_color_default(way);
-
-				// detect mouse hover
-				if let Some(mouse) = resp.hover_pos() {
-					let mouse_dist = distance_to_segment(mouse, points);
-					if mouse_dist < width {
-						self.state.hovered = Some(way.id);
-					}
+		// the selected object may have been removed by undo
+		if self.state.selected.is_some_and(|e| !self.document.contains(e)) {
+			self.state.selected = None;
+			self.state.edit_window_pos = None;
+		}
+
+		// only what the index finds around the visible part of the map is drawn and hovered
+		self.state.projection.update(projector, self.document.revision());
+		let data = self.document.data();
+		let index = self.document.index();
+		let projection = Projection::new(&self.state.projection, data, projector);
+		let mut visible_ways = HashSet::new();
+		let mut visible_nodes = HashSet::new();
+		for item in locate(index, projector, resp.rect.expand(CULLING_MARGIN * self.scale_factor)) {
+			match item {
+				// minor features are hidden when zoomed out, unless they are selected
+				Item::Way(id) if self.detail.shows(&data.ways[&id].tags, self.zoom) || self.state.selected == Some(ElementRef::Way(id)) => {
+					visible_ways.insert(id);
+				},
+				Item::Node(id) if self.detail.shows_points(self.zoom) => { visible_nodes.insert(id); },
+				_ => {},
+			}
+		}
+
+		// closed ways with area tags and multipolygons, drawn below the ways
+		let revision = self.document.revision();
+		let multipolygons = &mut self.state.multipolygons;
+		let mut areas: Vec<Area> = visible_ways.iter().map(|id| &data.ways[id])
+			.filter(|w| visual::is_area(w))
+			.map(|w| Area {
+				element: ElementRef::Way(w.id),
+				polygons: vec![Polygon::new(&w.nodes, &projection)],
+				style: way_style(self.style, w, self.zoom),
+				layer: order::layer(&w.tags),
+			})
+			.chain(self.document.relations().values()
+				.filter(|r| multipolygon::is_multipolygon(r) && self.detail.shows(&r.tags, self.zoom))
+				.filter(|r| r.members.iter().any(|m| matches!(m.element, ElementRef::Way(id) if visible_ways.contains(&id))))
+				.map(|r| Area {
+					element: ElementRef::Relation(r.id),
+					polygons: multipolygons.get(r, data, revision).project(&projection),
+					style: self.style.style(Subject::Relation { area: true }, &r.tags, self.zoom),
+					layer: order::layer(&r.tags),
+				}))
+			.collect();
+
+		if simplify {
+			for polygon in areas.iter_mut().flat_map(|a| &mut a.polygons) {
+				polygon.simplify(SIMPLIFY_TOLERANCE);
+			}
+		}
+
+		// by layer and z-index, then large areas first so that the ones inside them stay visible
+		let size = |polygons: &[Polygon]| polygons.iter().map(Polygon::area).sum::<f32>();
+		areas.sort_by(|a, b| a.layer.cmp(&b.layer)
+			.then(a.style.z_index.total_cmp(&b.style.z_index))
+			.then(size(&b.polygons).total_cmp(&size(&a.polygons)))
+			.then(a.element.cmp(&b.element)));
+
+		for area in &areas {
+			let width = area.style.width * self.scale_factor;
+			for polygon in &area.polygons {
+				ui.painter().extend(visual::area(polygon, area.style.fill(), area.style.color, width));
+			}
+		}
+
+		// lines are drawn above all areas, by layer, bridge or tunnel and road class
+		let mut ways: Vec<(&Way, Style, DrawOrder)> = visible_ways.iter().map(|id| &data.ways[id])
+			.filter(|w| !visual::is_area(w))
+			.map(|w| {
+				let mut style = way_style(self.style, w, self.zoom);
+				let order = DrawOrder::new(w.id, &w.tags, &style);
+				order::adjust(&mut style, order.structure);
+				(w, style, order)
+			})
+			.collect();
+		ways.sort_by_key(|(_, _, order)| *order);
+		// position in the drawing order, the topmost way under the mouse is hovered
+		let rank: HashMap<Id, usize> = ways.iter().enumerate().map(|(i, (w, _, _))| (w.id, i)).collect();
+
+		// casings of all ways on a level go below their lines
+		let mut casings = Vec::new();
+		let mut lines = Vec::new();
+		let mut level = ways.first().map(|(_, _, o)| o.level());
+
+		for (way, style, order) in ways {
+			if level != Some(order.level()) {
+				ui.painter().extend(casings.drain(..));
+				ui.painter().extend(lines.drain(..));
+				level = Some(order.level());
+			}
+
+			let mut points = projection.nodes(&way.nodes);
+			if simplify {
+				points = detail::simplify(&points, SIMPLIFY_TOLERANCE);
+			}
+			let width = style.width * self.scale_factor;
+			let color = style.color;
+
+			// draw osm data based on selected visualization method
+			let shapes = match self.visualization {
+				Visualization::Default => {
+					casings.extend(visual::casing(&points, &style, self.scale_factor));
+					visual::line(way, &points, &style, self.scale_factor)
+				},
+				Visualization::Sidewalks => visual::sidewalks(way, &points, color, width),
+				Visualization::Cycleways => visual::cycleways(way, &points, color, width),
+				Visualization::Parking => visual::parking(way, &points, color, width),
+				Visualization::Surface => visual::surfaces(way, &points, color, width),
+			};
+
+			// draw selection
+			if self.state.selected == Some(ElementRef::Way(way.id)) {
+				shapes_top.extend(visual::default(&points, SELECTION_COLOR, width + SELECTION_SIZE_INCREASE));
+			}
+
+			lines.extend(shapes);
+		}
+
+		// submit shapes
+		ui.painter().extend(casings);
+		ui.painter().extend(lines);
+
+		let mut pois: Vec<&Node> = visible_nodes.iter().map(|id| &data.nodes[id]).filter(|n| visual::is_poi(n)).collect();
+		pois.sort_by_key(|n| n.id);
+		for node in &pois {
+			let Some(pos) = projection.node(node.id) else { continue };
+			let color = self.style.style(Subject::Node, &node.tags, self.zoom).color;
+			ui.painter().extend(visual::node(pos, color, POI_RADIUS * self.scale_factor));
+		}
+
+		// street names first, then names and house numbers of areas, larger areas first, then of nodes
+		if self.detail.shows_labels(self.zoom) {
+			let mut labels = Labels::default();
+
+			let mut streets: Vec<&Way> = visible_ways.iter().map(|id| &data.ways[id])
+				.filter(|w| !visual::is_area(w) && w.tags.contains_key("highway") && w.tags.contains_key("name"))
+				.collect();
+			streets.sort_by_key(|w| (Reverse(order::road_class(&w.tags)), w.id));
+			for way in streets {
+				labels.along(ui, &way.tags["name"], &projection.nodes(&way.nodes));
+			}
+
+			let mut labeled: Vec<(&Area, &Polygon)> = areas.iter()
+				.filter_map(|a| Some((a, a.polygons.iter().max_by(|x, y| x.area().total_cmp(&y.area()))?)))
+				.collect();
+			labeled.sort_by(|a, b| b.1.area().total_cmp(&a.1.area()));
+
+			let house_numbers = self.detail.shows_house_numbers(self.zoom);
+			for (area, polygon) in &labeled {
+				if let Some(text) = self.document.tags(area.element).and_then(|t| label::text(t, house_numbers)) {
+					labels.at(ui, &text, polygon.centroid());
+				}
+			}
+
+			// under the marker, which would hide the text
+			for node in &pois {
+				if let (Some(text), Some(pos)) = (label::text(&node.tags, house_numbers), projection.node(node.id)) {
+					let top = pos + Vec2::new(0.0, POI_RADIUS * self.scale_factor + LABEL_PADDING);
+					labels.below(ui, &text, top);
 				}
+			}
+
+			ui.painter().extend(labels.into_shapes());
+		}
 
-				// draw osm data based on selected visualization method
-				let shapes = match self.visualization {
-					Visualization::Default => visual::default(points, color, width),
-					Visualization::Sidewalks => {
-						if let Some(mouse) = resp.hover_pos() {
-							if self.state.selected == Some(way.id) && distance_to_segment(mouse, points) < width {
-                                self.state.edit_window_pos = Some(mouse);
-                            }
+		// vertices of the selected way, or of the ways a selected vertex is part of
+		let vertex_ways: Vec<&Way> = match self.state.selected {
+			Some(ElementRef::Way(id)) => data.ways.get(&id).into_iter().collect(),
+			Some(ElementRef::Node(id)) => ways_at(index, data, id),
+			_ => Vec::new(),
+		};
+		let vertices: HashSet<Id> = vertex_ways.iter().flat_map(|w| w.nodes.iter().copied()).collect();
+		for pos in vertices.iter().filter_map(|id| projection.node(*id)) {
+			shapes_top.extend(visual::node(pos, VERTEX_COLOR, VERTEX_RADIUS * self.scale_factor));
+		}
+
+		if let Some(ElementRef::Node(id)) = self.state.selected {
+			if let Some(pos) = projection.node(id) {
+				shapes_top.push(visual::node_highlight(pos, SELECTION_COLOR, node_radius(&data.nodes[&id]) * self.scale_factor));
+			}
+		}
+
+		// closest way and node under the mouse among what the index finds around it,
+		// nodes are hovered before the ways they are part of
+		if let Some(mouse) = resp.hover_pos() {
+			let tolerance = (HOVER_DISTANCE * self.scale_factor).max(NODE_HOVER_DISTANCE);
+			// ways by drawing order and distance, nodes by distance
+			let mut hovered_way: Option<(Id, usize, f32)> = None;
+			let mut hovered_node: Option<(Id, f32)> = None;
+
+			for item in locate(index, projector, Rect::from_center_size(mouse, Vec2::splat(tolerance * 2.0))) {
+				match item {
+					Item::Segment(id, i) if visible_ways.contains(&id) && !visual::is_area(&data.ways[&id]) => {
+						let way = &data.ways[&id];
+						// the index only has segments between loaded nodes
+						let [Some(a), Some(b)] = [way.nodes[i], way.nodes[i + 1]].map(|n| projection.node(n)) else { continue };
+						let segment = [a, b];
+						let distance = distance_to_segment(mouse, segment);
+						let width = way_style(self.style, way, self.zoom).width * self.scale_factor;
+
+						let above = |(_, r, d): (Id, usize, f32)| rank[&id] > r || (rank[&id] == r && distance < d);
+						if distance < width && hovered_way.is_none_or(above) {
+							hovered_way = Some((id, rank[&id], distance));
 						}
+					},
+					Item::Node(id) if vertices.contains(&id) || (visible_nodes.contains(&id) && visual::is_poi(&data.nodes[&id])) => {
+						let Some(pos) = projection.node(id) else { continue };
+						let distance = mouse.distance(pos);
+						let radius = node_radius(&data.nodes[&id]) * self.scale_factor;
 
-						visual::sidewalks(way, points, color, width)
+						if distance < radius.max(NODE_HOVER_DISTANCE) && hovered_node.is_none_or(|(_, d)| distance < d) {
+							hovered_node = Some((id, distance));
+						}
 					},
-				};
-
-				// draw selection
-				if self.state.selected == Some(way.id) {
-					shapes_top.push(Shape::LineSegment {
-						points,
-						stroke: PathStroke::new(width + SELECTION_SIZE_INCREASE, SELECTION_COLOR),
-					});
+					_ => {},
 				}
+			}
+
+			if let Some((id, _, _)) = hovered_way {
+				self.state.hovered = Some(ElementRef::Way(id));
+
+				// open the editing window where the selected way is hovered
+				if self.visualization != Visualization::Default && self.state.selected == Some(ElementRef::Way(id)) {
+					self.state.edit_window_pos = Some(mouse);
+				}
+			}
+
+			if let Some((id, _)) = hovered_node {
+				self.state.hovered = Some(ElementRef::Node(id));
+			}
+		}
+
+		// areas are hovered anywhere inside, ways on top of them and smaller areas take precedence
+		if let (None, Some(mouse)) = (self.state.hovered, resp.hover_pos()) {
+			self.state.hovered = areas.iter()
+				.flat_map(|a| a.polygons.iter().map(move |p| (a.element, p)))
+				.filter(|(_, p)| p.contains(mouse))
+				.min_by(|a, b| a.1.area().total_cmp(&b.1.area()))
+				.map(|(element, _)| element);
+		}
+
+		if let Some(area) = areas.iter().find(|a| Some(a.element) == self.state.selected) {
+			let width = area.style.width * self.scale_factor;
+			for polygon in &area.polygons {
+				shapes_top.extend(visual::outline(polygon, SELECTION_COLOR, width + SELECTION_SIZE_INCREASE));
+			}
+		} else if let Some(ElementRef::Relation(id)) = self.state.selected {
+			// other relations, selected from the tags window, are shown by their member ways
+			let members = self.document.relations()[&id].members.iter().filter_map(|m| match m.element {
+				ElementRef::Way(id) => data.ways.get(&id),
+				_ => None,
+			});
+			for way in members {
+				let width = way_style(self.style, way, self.zoom).width * self.scale_factor;
+				shapes_top.extend(visual::default(&projection.nodes(&way.nodes), SELECTION_COLOR, width + SELECTION_SIZE_INCREASE));
+			}
+		}
+
+		// draw hovered object
+		if let Some(area) = areas.iter().find(|a| Some(a.element) == self.state.hovered) {
+			let width = area.style.width * self.scale_factor + HOVER_SIZE_INCREASE;
+			for polygon in &area.polygons {
+				shapes_top.extend(visual::outline(polygon, HOVER_COLOR, width));
+			}
+		} else if let Some(ElementRef::Way(id)) = self.state.hovered {
+			let way = &data.ways[&id];
+			let points = projection.nodes(&way.nodes);
+			let width = way_style(self.style, way, self.zoom).width * self.scale_factor + HOVER_SIZE_INCREASE;
 
-				// submit shapes
-				ui.painter().extend(shapes);
+			shapes_top.extend(visual::default(&points, HOVER_COLOR, width));
+		} else if let Some(ElementRef::Node(id)) = self.state.hovered {
+			if let Some(pos) = projection.node(id) {
+				shapes_top.push(visual::node_highlight(pos, HOVER_COLOR, node_radius(&data.nodes[&id]) * self.scale_factor));
 			}
 		}
 
+		if self.visualization == Visualization::Surface {
+			visual::surface_legend(ui, &mut self.state.surfaces, self.document.data(), self.document.revision());
+		}
+
 		// display editing window
-		if let Some(pos) = self.state.edit_window_pos {
+		if let (Some(pos), Some(ElementRef::Way(id))) = (self.state.edit_window_pos, self.state.selected) {
+			let mut tags = self.document.data().ways[&id].tags.clone();
 			let window_open = match self.visualization {
-				Visualization::Sidewalks => visual::sidewalks_ui(ui, pos),
+				Visualization::Sidewalks => visual::sidewalks_ui(ui, pos, &mut tags),
+				Visualization::Cycleways => visual::cycleways_ui(ui, pos, &mut tags),
+				Visualization::Parking => visual::parking_ui(ui, pos, &mut tags),
+				Visualization::Surface => visual::surface_ui(ui, pos, &mut tags),
 				_ => false,
 			};
 
+			if tags != self.document.data().ways[&id].tags {
+				self.history.execute(self.document, Command::SetWayTags(id, tags));
+			}
+
 			if !window_open {
 				self.state.edit_window_pos = None;
 			}
 		}
 
-		// draw hovered way
 		if let Some(hover) = self.state.hovered {
-			let way = &self.osm_data.ways[&hover];
-
-			for v in way.nodes.windows(2) {
-				let p1 = projector.project(coordinate_to_pos(&self.osm_data.nodes[&v[0]].pos)).to_pos2();
-				let p2 = projector.project(coordinate_to_pos(&self.osm_data.nodes[&v[1]].pos)).to_pos2();
-				let width = visual::determine_width_default(way) * self.scale_factor + HOVER_SIZE_INCREASE;
-				
-				shapes_top.extend(
-					visual::default([p1, p2], HOVER_COLOR, width)
-				);
-			}
-
-			if resp.clicked() && way_is_relevant(&way.tags, self.visualization) {
+			let relevant = self.document.tags(hover).is_some_and(|tags| is_relevant(tags, self.visualization));
+			if resp.clicked() && relevant {
 				self.state.selected = Some(hover);
 			}
 		} else if resp.clicked() {
@@ -116,10 +383,21 @@ impl Plugin for EditorPlugin<'_> {
 	}
 }
 
-fn way_is_relevant(tags: &Tags, visualization: Visualization) -> bool {
+fn way_style(style: &StyleSheet, way: &Way, zoom: f64) -> Style {
+	style.style(Subject::Way { area: visual::is_area(way) }, &way.tags, zoom)
+}
+
+fn node_radius(node: &Node) -> f32 {
+	if visual::is_poi(node) { POI_RADIUS } else { VERTEX_RADIUS }
+}
+
+fn is_relevant(tags: &Tags, visualization: Visualization) -> bool {
 	match visualization {
 		Visualization::Default => true,
 		Visualization::Sidewalks => visual::sidewalks_relevant(tags),
+		Visualization::Cycleways => visual::cycleways_relevant(tags),
+		Visualization::Parking => visual::parking_relevant(tags),
+		Visualization::Surface => visual::surface_relevant(tags),
 	}
 }
 
@@ -127,6 +405,32 @@ pub fn coordinate_to_pos(c: &Coordinate) -> Position {
 	Position::from_lon_lat(c.lon, c.lat)
 }
 
+// index entries that may be drawn inside a rectangle on screen
+fn locate<'a>(index: &'a SpatialIndex, projector: &Projector, rect: Rect) -> impl Iterator<Item = Item> + 'a {
+	let corner = |p: Pos2| {
+		let position = projector.unproject(p.to_vec2());
+		[position.lon(), position.lat()]
+	};
+	index.locate(corner(rect.min), corner(rect.max))
+}
+
+// ways a node is part of, their segments ending at the node are found at its position
+fn ways_at<'a>(index: &SpatialIndex, data: &'a OsmData, node: Id) -> Vec<&'a Way> {
+	let Some(n) = data.nodes.get(&node) else { return Vec::new() };
+	let p = [n.pos.lon, n.pos.lat];
+
+	let mut ids: Vec<Id> = index.locate(p, p)
+		.filter_map(|item| match item {
+			Item::Segment(way, _) => Some(way),
+			_ => None,
+		})
+		.collect();
+	ids.sort();
+	ids.dedup();
+
+	ids.iter().map(|id| &data.ways[id]).filter(|w| w.nodes.contains(&node)).collect()
+}
+
 fn distance_to_segment(p: Pos2, points: [Pos2; 2]) -> f32 {
 	let x = points[0];
 	let y = points[1];
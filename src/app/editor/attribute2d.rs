@@ -1,24 +1,64 @@
-use super::consts::*;
-use eframe::egui::Color32;
 use osm_parser::Tags;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
 pub struct Attribute2D {
 	pub left: TagValue,
 	pub right: TagValue,
 }
 
+// free-form value of a nested key: sidewalk:left:*surface*=asphalt
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SubAttribute2D {
+	pub left: Option<String>,
+	pub right: Option<String>,
+}
+
+// side-dependent key and the values it accepts
+#[derive(Debug)]
+pub struct SideKey {
+	pub key: &'static str,
+	pub values: &'static [TagValue],
+	// whether the base key takes side names, sidewalk=*left*
+	pub suffixes: bool,
+}
+
+pub const SIDEWALK: SideKey = SideKey {
+	key: "sidewalk",
+	values: &[TagValue::Yes, TagValue::No, TagValue::Separate],
+	suffixes: true,
+};
+
+pub const CYCLEWAY: SideKey = SideKey {
+	key: "cycleway",
+	values: &[TagValue::Lane, TagValue::Track, TagValue::SharedLane, TagValue::ShareBusway, TagValue::Separate, TagValue::No],
+	suffixes: false,
+};
+
+pub const PARKING: SideKey = SideKey {
+	key: "parking",
+	values: &[TagValue::Lane, TagValue::StreetSide, TagValue::OnKerb, TagValue::HalfOnKerb, TagValue::Shoulder, TagValue::Separate, TagValue::No],
+	suffixes: false,
+};
+
 // tag value: sidewalk:left=*yes*
-#[derive(Debug, Default, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
 pub enum TagValue {
 	Yes,
 	No,
 	Separate,
+	Lane,
+	Track,
+	SharedLane,
+	ShareBusway,
+	StreetSide,
+	OnKerb,
+	HalfOnKerb,
+	Shoulder,
 	#[default] Unknown,
 }
 
 // tag suffix, sidewalk:*left*=yes
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
 pub enum TagSuffix {
 	Left,
 	Right,
@@ -28,25 +68,91 @@ pub enum TagSuffix {
 	#[default] Unknown,
 }
 
+impl TagValue {
+	// value as written in tags, unknown is expressed by omitting the tag
+	pub fn as_str(&self) -> Option<&'static str> {
+		match self {
+			TagValue::Yes => Some("yes"),
+			TagValue::No => Some("no"),
+			TagValue::Separate => Some("separate"),
+			TagValue::Lane => Some("lane"),
+			TagValue::Track => Some("track"),
+			TagValue::SharedLane => Some("shared_lane"),
+			TagValue::ShareBusway => Some("share_busway"),
+			TagValue::StreetSide => Some("street_side"),
+			TagValue::OnKerb => Some("on_kerb"),
+			TagValue::HalfOnKerb => Some("half_on_kerb"),
+			TagValue::Shoulder => Some("shoulder"),
+			TagValue::Unknown => None,
+		}
+	}
+
+	pub fn label(&self) -> &'static str {
+		self.as_str().unwrap_or("unknown")
+	}
+}
+
 impl From<&String> for TagValue {
 	fn from(value: &String) -> Self {
 		match value.as_str() {
 			"yes" => TagValue::Yes,
 			"no" | "none" => TagValue::No,
 			"separate" => TagValue::Separate,
+			"lane" => TagValue::Lane,
+			"track" => TagValue::Track,
+			"shared_lane" => TagValue::SharedLane,
+			"share_busway" => TagValue::ShareBusway,
+			"street_side" => TagValue::StreetSide,
+			"on_kerb" => TagValue::OnKerb,
+			"half_on_kerb" => TagValue::HalfOnKerb,
+			"shoulder" => TagValue::Shoulder,
 			_ => TagValue::Unknown,
 		}
 	}
 }
 
-#[allow(clippy::from_over_into)]
-impl Into<Color32> for TagValue {
-	fn into(self) -> Color32 {
+impl SideKey {
+	// values selectable in editing windows
+	pub fn choices(&self) -> impl Iterator<Item = TagValue> + '_ {
+		self.values.iter().copied().chain([TagValue::Unknown])
+	}
+
+	// parses a value, rejecting those outside of this key's vocabulary
+	pub fn value(&self, value: &String) -> Option<TagValue> {
+		let value = TagValue::from(value);
+		self.values.contains(&value).then_some(value)
+	}
+
+	// parses the value of the base key, cycleway=*lane* or sidewalk=*left*
+	fn base(&self, value: &String) -> Option<Attribute2D> {
+		match TagSuffix::from(value) {
+			TagSuffix::Left | TagSuffix::Right | TagSuffix::Both if !self.suffixes => None,
+			TagSuffix::Unknown if !self.suffixes => self.value(value).map(|v| Attribute2D { left: v, right: v }),
+			TagSuffix::Unknown => None,
+			suffix => Some(Attribute2D::from(suffix)),
+		}
+	}
+
+	fn side_keys(&self) -> [String; 4] {
+		let key = self.key;
+		[
+			key.to_string(),
+			format!("{key}:left"),
+			format!("{key}:right"),
+			format!("{key}:both"),
+		]
+	}
+}
+
+impl TagSuffix {
+	pub fn as_str(&self) -> Option<&'static str> {
 		match self {
-			TagValue::Yes => SIDEWALK_YES_COLOR,
-			TagValue::No => SIDEWALK_NO_COLOR,
-			TagValue::Separate => SIDEWALK_SEPARATE_COLOR,
-			TagValue::Unknown => SIDEWALK_UNKNOWN_COLOR,
+			TagSuffix::Left => Some("left"),
+			TagSuffix::Right => Some("right"),
+			TagSuffix::Both => Some("both"),
+			TagSuffix::Separate => Some("separate"),
+			TagSuffix::No => Some("no"),
+			TagSuffix::Unknown => None,
 		}
 	}
 }
@@ -102,24 +208,258 @@ impl From<TagSuffix> for Attribute2D {
 
 
 impl Attribute2D {
-	pub fn new(tags: &Tags, tag: &str) -> Self {
+	pub fn new(tags: &Tags, key: &SideKey) -> Self {
 		let mut attribute2d = Attribute2D::default();
+		let [base, left, right, both] = key.side_keys();
 
-		if let Some(v) = tags.get("sidewalk") {
-			attribute2d = Attribute2D::from(TagSuffix::from(v));
+		if let Some(v) = tags.get(&base) {
+			attribute2d = key.base(v).unwrap_or_default();
 		}
-		if let Some(v) = tags.get(&format!("{tag}:left")) {
-			attribute2d.left = TagValue::from(v);
+		if let Some(v) = tags.get(&left) {
+			attribute2d.left = key.value(v).unwrap_or_default();
 		}
-		if let Some(v) = tags.get(&format!("{tag}:right")) {
-			attribute2d.right = TagValue::from(v);
+		if let Some(v) = tags.get(&right) {
+			attribute2d.right = key.value(v).unwrap_or_default();
 		}
-		if let Some(v) = tags.get(&format!("{tag}:both")) {
-			let v = TagValue::from(v);
+		if let Some(v) = tags.get(&both) {
+			let v = key.value(v).unwrap_or_default();
 			attribute2d.left = v;
 			attribute2d.right = v;
 		}
 
 		attribute2d
 	}
+
+	// inverse of From<TagSuffix>, limited to the combinations that are canonically tagged on the base key
+	pub fn suffix(&self) -> Option<TagSuffix> {
+		match (self.left, self.right) {
+			(TagValue::Yes, TagValue::Yes) => Some(TagSuffix::Both),
+			(TagValue::Yes, TagValue::No) => Some(TagSuffix::Left),
+			(TagValue::No, TagValue::Yes) => Some(TagSuffix::Right),
+			(TagValue::No, TagValue::No) => Some(TagSuffix::No),
+			_ => None,
+		}
+	}
+
+	// minimal canonical tag set for this attribute
+	pub fn to_tags(self, key: &SideKey) -> Tags {
+		let mut tags = Tags::new();
+		let [base, left, right, both] = key.side_keys();
+
+		if let Some(suffix) = self.suffix().filter(|_| key.suffixes).and_then(|s| s.as_str()) {
+			tags.insert(base, suffix.to_string());
+		} else if self.left == self.right {
+			if let Some(v) = self.left.as_str() {
+				tags.insert(both, v.to_string());
+			}
+		} else {
+			if let Some(v) = self.left.as_str() {
+				tags.insert(left, v.to_string());
+			}
+			if let Some(v) = self.right.as_str() {
+				tags.insert(right, v.to_string());
+			}
+		}
+
+		tags
+	}
+
+	// writes the attribute back in canonical form and removes conflicting keys
+	pub fn apply(&self, tags: &mut Tags, key: &SideKey) {
+		for k in key.side_keys() {
+			tags.remove(&k);
+		}
+
+		tags.extend(self.to_tags(key));
+	}
+
+	// rewrites existing tagging into canonical form, returns false if unrecognized values prevent it
+	pub fn normalize(tags: &mut Tags, key: &SideKey) -> bool {
+		let [base, left, right, both] = key.side_keys();
+		let recognized = tags.get(&base).is_none_or(|v| key.base(v).is_some())
+			&& [left, right, both].iter().all(|k| tags.get(k).is_none_or(|v| key.value(v).is_some()));
+
+		if recognized {
+			Attribute2D::new(tags, key).apply(tags, key);
+		}
+
+		recognized
+	}
+}
+
+impl SubAttribute2D {
+	// reads key:sub, key:left:sub, key:right:sub and key:both:sub, same precedence as Attribute2D
+	pub fn new(tags: &Tags, key: &SideKey, sub: &str) -> Self {
+		let mut attribute = SubAttribute2D::default();
+		let [base, left, right, both] = sub_keys(key, sub);
+
+		if let Some(v) = tags.get(&base) {
+			attribute.left = Some(v.clone());
+			attribute.right = Some(v.clone());
+		}
+		if let Some(v) = tags.get(&left) {
+			attribute.left = Some(v.clone());
+		}
+		if let Some(v) = tags.get(&right) {
+			attribute.right = Some(v.clone());
+		}
+		if let Some(v) = tags.get(&both) {
+			attribute.left = Some(v.clone());
+			attribute.right = Some(v.clone());
+		}
+
+		attribute
+	}
+
+	pub fn to_tags(&self, key: &SideKey, sub: &str) -> Tags {
+		let mut tags = Tags::new();
+		let [_, left, right, both] = sub_keys(key, sub);
+
+		if self.left == self.right {
+			if let Some(v) = &self.left {
+				tags.insert(both, v.clone());
+			}
+		} else {
+			if let Some(v) = &self.left {
+				tags.insert(left, v.clone());
+			}
+			if let Some(v) = &self.right {
+				tags.insert(right, v.clone());
+			}
+		}
+
+		tags
+	}
+
+	pub fn apply(&self, tags: &mut Tags, key: &SideKey, sub: &str) {
+		for k in sub_keys(key, sub) {
+			tags.remove(&k);
+		}
+
+		tags.extend(self.to_tags(key, sub));
+	}
+}
+
+fn sub_keys(key: &SideKey, sub: &str) -> [String; 4] {
+	let key = key.key;
+	[
+		format!("{key}:{sub}"),
+		format!("{key}:left:{sub}"),
+		format!("{key}:right:{sub}"),
+		format!("{key}:both:{sub}"),
+	]
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::test_util::tags;
+
+	const SUFFIXES: [TagSuffix; 6] = [
+		TagSuffix::Left, TagSuffix::Right, TagSuffix::Both,
+		TagSuffix::Separate, TagSuffix::No, TagSuffix::Unknown,
+	];
+
+	#[test]
+	fn round_trip_values() {
+		for key in [&SIDEWALK, &CYCLEWAY, &PARKING] {
+			for left in key.choices() {
+				for right in key.choices() {
+					let attr = Attribute2D { left, right };
+					let parsed = Attribute2D::new(&attr.to_tags(key), key);
+					assert_eq!(parsed, attr, "{}", key.key);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn round_trip_suffixes() {
+		for suffix in SUFFIXES {
+			let attr = Attribute2D::from(suffix);
+			let parsed = Attribute2D::new(&attr.to_tags(&SIDEWALK), &SIDEWALK);
+			assert_eq!(parsed, attr, "{suffix:?}");
+		}
+	}
+
+	#[test]
+	fn suffix_inverse() {
+		for suffix in SUFFIXES {
+			if let Some(inverse) = Attribute2D::from(suffix).suffix() {
+				assert_eq!(Attribute2D::from(inverse), Attribute2D::from(suffix));
+			}
+		}
+	}
+
+	#[test]
+	fn canonical_tags() {
+		let cases = [
+			(TagValue::Yes, TagValue::Yes, tags(&[("sidewalk", "both")])),
+			(TagValue::Yes, TagValue::No, tags(&[("sidewalk", "left")])),
+			(TagValue::No, TagValue::Yes, tags(&[("sidewalk", "right")])),
+			(TagValue::No, TagValue::No, tags(&[("sidewalk", "no")])),
+			(TagValue::Separate, TagValue::Separate, tags(&[("sidewalk:both", "separate")])),
+			(TagValue::Separate, TagValue::No, tags(&[("sidewalk:left", "separate"), ("sidewalk:right", "no")])),
+			(TagValue::Yes, TagValue::Unknown, tags(&[("sidewalk:left", "yes")])),
+			(TagValue::Unknown, TagValue::Unknown, tags(&[])),
+		];
+
+		for (left, right, expected) in cases {
+			assert_eq!(Attribute2D { left, right }.to_tags(&SIDEWALK), expected, "{left:?} {right:?}");
+		}
+	}
+
+	#[test]
+	fn normalize_mixed() {
+		let mut t = tags(&[("highway", "residential"), ("sidewalk", "both"), ("sidewalk:left", "no")]);
+		assert!(Attribute2D::normalize(&mut t, &SIDEWALK));
+		assert_eq!(t, tags(&[("highway", "residential"), ("sidewalk", "right")]));
+
+		let mut t = tags(&[("sidewalk:left", "separate"), ("sidewalk:right", "separate")]);
+		assert!(Attribute2D::normalize(&mut t, &SIDEWALK));
+		assert_eq!(t, tags(&[("sidewalk:both", "separate")]));
+
+		let mut t = tags(&[("sidewalk", "none"), ("sidewalk:left:surface", "asphalt")]);
+		assert!(Attribute2D::normalize(&mut t, &SIDEWALK));
+		assert_eq!(t, tags(&[("sidewalk", "no"), ("sidewalk:left:surface", "asphalt")]));
+	}
+
+	#[test]
+	fn normalize_keeps_unrecognized() {
+		let original = tags(&[("sidewalk", "both"), ("sidewalk:right", "maybe")]);
+		let mut t = original.clone();
+		assert!(!Attribute2D::normalize(&mut t, &SIDEWALK));
+		assert_eq!(t, original);
+	}
+
+	#[test]
+	fn key_vocabulary() {
+		let t = tags(&[("cycleway", "lane"), ("parking:both", "lane"), ("parking:right", "track")]);
+		assert_eq!(Attribute2D::new(&t, &CYCLEWAY), Attribute2D { left: TagValue::Lane, right: TagValue::Lane });
+		assert_eq!(Attribute2D::new(&t, &PARKING), Attribute2D { left: TagValue::Lane, right: TagValue::Lane });
+		assert_eq!(Attribute2D::new(&t, &SIDEWALK), Attribute2D::default());
+
+		let t = tags(&[("cycleway", "both"), ("parking:left", "track")]);
+		assert_eq!(Attribute2D::new(&t, &CYCLEWAY), Attribute2D::default());
+		assert_eq!(Attribute2D::new(&t, &PARKING), Attribute2D::default());
+
+		let attr = Attribute2D { left: TagValue::No, right: TagValue::No };
+		assert_eq!(attr.to_tags(&CYCLEWAY), tags(&[("cycleway:both", "no")]));
+	}
+
+	#[test]
+	fn nested_keys() {
+		let t = tags(&[("sidewalk:surface", "asphalt"), ("sidewalk:right:surface", "paving_stones")]);
+		let sub = SubAttribute2D::new(&t, &SIDEWALK, "surface");
+		assert_eq!(sub.left.as_deref(), Some("asphalt"));
+		assert_eq!(sub.right.as_deref(), Some("paving_stones"));
+
+		let mut t = tags(&[("parking:left:orientation", "diagonal"), ("parking:right:orientation", "parallel")]);
+		let mut sub = SubAttribute2D::new(&t, &PARKING, "orientation");
+		assert_eq!(sub.left.as_deref(), Some("diagonal"));
+		sub.left = Some("parallel".to_string());
+		sub.apply(&mut t, &PARKING, "orientation");
+		assert_eq!(t, tags(&[("parking:both:orientation", "parallel")]));
+	}
 }
@@ -1,12 +1,57 @@
 pub mod osm;
 
 use eframe::egui::Color32;
+use std::time::Duration;
 
 pub const HOVER_COLOR: Color32 = Color32::from_rgb(100, 200, 255);
 pub const HOVER_SIZE_INCREASE: f32 = 1.0;
 pub const SELECTION_COLOR: Color32 = Color32::from_rgb(40, 180, 255);
 pub const SELECTION_SIZE_INCREASE: f32 = 2.0;
 pub const DEFAULT_COLOR: Color32 = Color32::GRAY;
+pub const VERTEX_RADIUS: f32 = 3.0;
+// nodes smaller than this are still hovered within this distance
+pub const NODE_HOVER_DISTANCE: f32 = 6.0;
+// farthest anything is hovered from the mouse, wider lines from the stylesheet are only hovered this close
+pub const HOVER_DISTANCE: f32 = 8.0;
+// objects this far outside the map are still drawn, so that wide lines do not pop in at the edges
+pub const CULLING_MARGIN: f32 = 20.0;
+pub const VERTEX_COLOR: Color32 = Color32::WHITE;
+pub const NODE_OUTLINE_COLOR: Color32 = Color32::from_rgb(40, 40, 40);
+pub const AREA_FILL_OPACITY: f32 = 0.3;
+// longest miter of an offset line, in multiples of the offset
+pub const MITER_LIMIT: f32 = 4.0;
+pub const ONEWAY_ARROW_COLOR: Color32 = Color32::from_rgb(108, 112, 213);
+pub const ONEWAY_ARROW_SPACING: f32 = 80.0;
+// half the length of an arrow in multiples of the line width
+pub const ONEWAY_ARROW_SIZE: f32 = 0.6;
+pub const ONEWAY_ARROW_MIN_SIZE: f32 = 2.5;
+// tunnels are drawn this much more transparent than the style says
+pub const TUNNEL_OPACITY: f32 = 0.4;
+pub const BRIDGE_CASING_COLOR: Color32 = Color32::from_rgb(60, 60, 60);
+// on each side, before the width is scaled
+pub const BRIDGE_CASING_WIDTH: f32 = 1.5;
+// how often a loaded stylesheet is checked for changes
+pub const STYLE_RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+//region level of detail
+pub const WIDTH_REFERENCE_ZOOM: f64 = 18.0;
+pub const WIDTH_ZOOM_STEP: f64 = 2.0;
+pub const MIN_WIDTH_SCALE: f32 = 0.25;
+pub const MAX_WIDTH_SCALE: f32 = 2.0;
+// in pixels, simplified lines stay this close to the original
+pub const SIMPLIFY_TOLERANCE: f32 = 1.0;
+//endregion
+
+//region labels
+pub const LABEL_SIZE: f32 = 12.0;
+pub const LABEL_COLOR: Color32 = Color32::from_rgb(40, 40, 40);
+pub const LABEL_HALO_COLOR: Color32 = Color32::WHITE;
+pub const LABEL_HALO_WIDTH: f32 = 1.0;
+// free space kept around each label
+pub const LABEL_PADDING: f32 = 2.0;
+// largest change of direction between neighbouring letters, in radians
+pub const LABEL_MAX_BEND: f32 = 0.5;
+//endregion
 
 //region sidewalk overlay
 pub const SIDEWALK_YES_COLOR: Color32 = Color32::LIGHT_GREEN;
@@ -14,3 +59,51 @@ pub const SIDEWALK_NO_COLOR: Color32 = Color32::LIGHT_GRAY;
 pub const SIDEWALK_SEPARATE_COLOR: Color32 = Color32::LIGHT_BLUE;
 pub const SIDEWALK_UNKNOWN_COLOR: Color32 = Color32::LIGHT_RED;
 //endregion
+
+//region cycleway overlay
+pub const CYCLEWAY_LANE_COLOR: Color32 = Color32::from_rgb(66, 135, 245);
+pub const CYCLEWAY_TRACK_COLOR: Color32 = Color32::from_rgb(140, 90, 230);
+pub const CYCLEWAY_SHARED_LANE_COLOR: Color32 = Color32::from_rgb(245, 205, 60);
+pub const CYCLEWAY_SHARE_BUSWAY_COLOR: Color32 = Color32::from_rgb(245, 145, 40);
+pub const CYCLEWAY_SEPARATE_COLOR: Color32 = Color32::LIGHT_BLUE;
+pub const CYCLEWAY_NO_COLOR: Color32 = Color32::LIGHT_GRAY;
+pub const CYCLEWAY_UNKNOWN_COLOR: Color32 = Color32::LIGHT_RED;
+//endregion
+
+//region surface overlay
+pub const SURFACE_PAVED_COLOR: Color32 = Color32::from_rgb(90, 90, 100);
+pub const SURFACE_CONCRETE_COLOR: Color32 = Color32::from_rgb(170, 170, 180);
+pub const SURFACE_PAVING_STONES_COLOR: Color32 = Color32::from_rgb(210, 140, 90);
+pub const SURFACE_SETT_COLOR: Color32 = Color32::from_rgb(160, 100, 60);
+pub const SURFACE_COMPACTED_COLOR: Color32 = Color32::from_rgb(200, 180, 100);
+pub const SURFACE_GRAVEL_COLOR: Color32 = Color32::from_rgb(180, 160, 120);
+pub const SURFACE_GROUND_COLOR: Color32 = Color32::from_rgb(140, 100, 50);
+pub const SURFACE_GRASS_COLOR: Color32 = Color32::from_rgb(100, 180, 80);
+pub const SURFACE_SAND_COLOR: Color32 = Color32::from_rgb(230, 210, 140);
+pub const SURFACE_WOOD_COLOR: Color32 = Color32::from_rgb(155, 115, 80);
+pub const SURFACE_OTHER_COLOR: Color32 = Color32::LIGHT_BLUE;
+pub const SURFACE_MISSING_COLOR: Color32 = Color32::from_rgb(255, 40, 160);
+// the halo around highways without a surface, added to the line width
+pub const SURFACE_MISSING_HALO: f32 = 6.0;
+pub const SMOOTHNESS_GOOD_COLOR: Color32 = Color32::from_rgb(60, 200, 80);
+pub const SMOOTHNESS_INTERMEDIATE_COLOR: Color32 = Color32::from_rgb(240, 220, 50);
+pub const SMOOTHNESS_BAD_COLOR: Color32 = Color32::from_rgb(245, 140, 40);
+pub const SMOOTHNESS_HORRIBLE_COLOR: Color32 = Color32::from_rgb(220, 40, 40);
+pub const SMOOTHNESS_IMPASSABLE_COLOR: Color32 = Color32::BLACK;
+//endregion
+
+//region parking overlay
+pub const PARKING_LANE_COLOR: Color32 = Color32::from_rgb(70, 110, 230);
+pub const PARKING_STREET_SIDE_COLOR: Color32 = Color32::from_rgb(120, 170, 255);
+pub const PARKING_ON_KERB_COLOR: Color32 = Color32::from_rgb(200, 120, 230);
+pub const PARKING_HALF_ON_KERB_COLOR: Color32 = Color32::from_rgb(160, 140, 240);
+pub const PARKING_SHOULDER_COLOR: Color32 = Color32::from_rgb(150, 200, 200);
+pub const PARKING_SEPARATE_COLOR: Color32 = Color32::LIGHT_BLUE;
+pub const PARKING_NO_COLOR: Color32 = Color32::LIGHT_GRAY;
+pub const PARKING_UNKNOWN_COLOR: Color32 = Color32::LIGHT_RED;
+pub const PARKING_NO_STOPPING_COLOR: Color32 = Color32::RED;
+pub const PARKING_NO_PARKING_COLOR: Color32 = Color32::from_rgb(255, 140, 0);
+pub const PARKING_RESTRICTED_COLOR: Color32 = Color32::YELLOW;
+pub const PARKING_RESTRICTION_UNKNOWN_COLOR: Color32 = Color32::DARK_GRAY;
+pub const PARKING_TICK_SPACING: f32 = 4.0;
+//endregion
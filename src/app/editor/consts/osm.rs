@@ -1,19 +1,8 @@
 #![allow(dead_code)]
 
-use eframe::egui::Color32;
-
+// used where no style rule matches, colors and widths come from the stylesheet
 pub const DEFAULT_WIDTH: f32 = 1.0;
-pub const PATH_WIDTH: f32 = 2.5;
-pub const SERVICE_ROAD_WIDTH: f32 = 4.0;
-pub const MINOR_ROAD_WIDTH: f32 = 5.0;
-pub const MAJOR_ROAD_WIDTH: f32 = 6.0;
-pub const BUILDING_WIDTH: f32 = 2.0;
-
-pub const BUILDING_COLOR: Color32 = Color32::from_rgb(224, 110, 95);
-pub const PATH_COLOR: Color32 = Color32::from_rgb(221, 204, 170);
-pub const FOOTWAY_COLOR: Color32 = Color32::WHITE;
-pub const STEPS_COLOR: Color32 = Color32::from_rgb(129, 210, 92);
-pub const TRACK_COLOR: Color32 = Color32::from_rgb(197, 181, 159);
+pub const POI_RADIUS: f32 = 4.0;
 
 //region highway
 // roads
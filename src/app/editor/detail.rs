@@ -0,0 +1,109 @@
+use super::consts::*;
+use eframe::egui::Pos2;
+use osm_parser::Tags;
+
+// zoom levels from which minor features are drawn, major roads and land use are always drawn
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LevelOfDetail {
+	pub minor_roads: f64,
+	pub paths: f64,
+	pub buildings: f64,
+	pub points: f64,
+	pub labels: f64,
+	pub house_numbers: f64,
+	// lines are simplified below this zoom
+	pub simplify_below: f64,
+}
+
+impl Default for LevelOfDetail {
+	fn default() -> Self {
+		Self { minor_roads: 13.0, paths: 15.0, buildings: 15.0, points: 17.0, labels: 16.0, house_numbers: 18.0, simplify_below: 17.0 }
+	}
+}
+
+impl LevelOfDetail {
+	pub fn shows(&self, tags: &Tags, zoom: f64) -> bool {
+		zoom >= self.min_zoom(tags)
+	}
+
+	pub fn shows_points(&self, zoom: f64) -> bool {
+		zoom >= self.points
+	}
+
+	pub fn shows_labels(&self, zoom: f64) -> bool {
+		zoom >= self.labels
+	}
+
+	pub fn shows_house_numbers(&self, zoom: f64) -> bool {
+		zoom >= self.house_numbers
+	}
+
+	pub fn simplifies(&self, zoom: f64) -> bool {
+		zoom < self.simplify_below
+	}
+
+	fn min_zoom(&self, tags: &Tags) -> f64 {
+		if tags.get("building").is_some_and(|b| b != "no") {
+			return self.buildings;
+		}
+
+		match tags.get("highway").map(String::as_str) {
+			Some("path" | "footway" | "cycleway" | "bridleway" | "steps" | "pedestrian" | "track") => self.paths,
+			Some("service" | "residential" | "living_street" | "unclassified") => self.minor_roads,
+			_ => 0.0,
+		}
+	}
+}
+
+// widths are set for WIDTH_REFERENCE_ZOOM and halve every WIDTH_ZOOM_STEP levels below it
+pub fn width_scale(zoom: f64) -> f32 {
+	let scale = 2f64.powf((zoom - WIDTH_REFERENCE_ZOOM) / WIDTH_ZOOM_STEP) as f32;
+	scale.clamp(MIN_WIDTH_SCALE, MAX_WIDTH_SCALE)
+}
+
+// Douglas-Peucker, the first and last point are always kept so closed rings stay closed
+pub fn simplify(points: &[Pos2], tolerance: f32) -> Vec<Pos2> {
+	if points.len() < 3 {
+		return points.to_vec();
+	}
+
+	let mut keep = vec![false; points.len()];
+	keep[0] = true;
+	keep[points.len() - 1] = true;
+	let mut ranges = vec![(0, points.len() - 1)];
+
+	while let Some((first, last)) = ranges.pop() {
+		let farthest = (first + 1..last)
+			.map(|i| (i, super::distance_to_segment(points[i], [points[first], points[last]])))
+			.max_by(|a, b| a.1.total_cmp(&b.1));
+
+		if let Some((i, _)) = farthest.filter(|(_, d)| *d > tolerance) {
+			keep[i] = true;
+			ranges.push((first, i));
+			ranges.push((i, last));
+		}
+	}
+
+	points.iter().zip(keep).filter(|(_, k)| *k).map(|(p, _)| *p).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn simplify_keeps_corners() {
+		let line = [Pos2::new(0.0, 0.0), Pos2::new(5.0, 0.2), Pos2::new(10.0, 0.0), Pos2::new(10.0, 10.0)];
+		assert_eq!(simplify(&line, 1.0), [line[0], line[2], line[3]]);
+
+		let ring = [Pos2::new(0.0, 0.0), Pos2::new(10.0, 0.0), Pos2::new(10.0, 10.0), Pos2::new(0.0, 10.0), Pos2::new(0.0, 0.0)];
+		assert_eq!(simplify(&ring, 1.0), ring);
+	}
+
+	#[test]
+	fn widths_follow_zoom() {
+		assert_eq!(width_scale(WIDTH_REFERENCE_ZOOM), 1.0);
+		assert_eq!(width_scale(WIDTH_REFERENCE_ZOOM - WIDTH_ZOOM_STEP), 0.5);
+		assert_eq!(width_scale(0.0), MIN_WIDTH_SCALE);
+	}
+}
@@ -0,0 +1,160 @@
+use super::consts::*;
+use eframe::egui::{FontId, Galley, Pos2, Rect, Shape, Ui, Vec2};
+use eframe::emath::Rot2;
+use eframe::epaint::TextShape;
+use osm_parser::Tags;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+// labels placed this frame, a label overlapping an earlier one is dropped so the first ones take precedence
+#[derive(Default)]
+pub struct Labels {
+	placed: Vec<Rect>,
+	shapes: Vec<Shape>,
+}
+
+impl Labels {
+	// letters follow the line, centered on it and reading left to right,
+	// nothing is placed when the line is too short or bends too much under the text
+	pub fn along(&mut self, ui: &Ui, text: &str, points: &[Pos2]) {
+		let galley = layout(ui, text);
+		let Some(glyphs) = galley.rows.first().map(|r| &r.glyphs) else { return };
+		let offsets: Vec<f32> = glyphs.iter().map(|g| g.pos.x + g.advance_width / 2.0).collect();
+
+		let Some(mut placement) = place_along(points, galley.size().x, &offsets) else { return };
+		if placement.last().zip(placement.first()).is_some_and(|(last, first)| last.0.x < first.0.x) {
+			let reversed: Vec<Pos2> = points.iter().rev().copied().collect();
+			let Some(upright) = place_along(&reversed, galley.size().x, &offsets) else { return };
+			placement = upright;
+		}
+
+		let height = galley.size().y;
+		let rects: Vec<Rect> = placement.iter().map(|(center, _)| Rect::from_center_size(*center, Vec2::splat(height))).collect();
+		if !self.fits(&rects) { return; }
+
+		for (glyph, (center, angle)) in glyphs.iter().zip(placement) {
+			let letter = layout(ui, &glyph.chr.to_string());
+			let pos = center - Rot2::from_angle(angle) * (letter.size() / 2.0);
+			self.text(pos, letter, angle);
+		}
+		self.placed.extend(rects);
+	}
+
+	// text centered on a point
+	pub fn at(&mut self, ui: &Ui, text: &str, center: Pos2) {
+		let galley = layout(ui, text);
+		let rect = Rect::from_center_size(center, galley.size());
+		self.place(rect, galley);
+	}
+
+	// text centered under a point, for nodes whose marker covers the point itself
+	pub fn below(&mut self, ui: &Ui, text: &str, top: Pos2) {
+		let galley = layout(ui, text);
+		let rect = Rect::from_min_size(top - Vec2::new(galley.size().x / 2.0, 0.0), galley.size());
+		self.place(rect, galley);
+	}
+
+	pub fn into_shapes(self) -> Vec<Shape> {
+		self.shapes
+	}
+
+	fn place(&mut self, rect: Rect, galley: Arc<Galley>) {
+		if !self.fits(&[rect]) { return; }
+
+		self.text(rect.min, galley, 0.0);
+		self.placed.push(rect);
+	}
+
+	fn fits(&self, rects: &[Rect]) -> bool {
+		!rects.iter().any(|r| self.placed.iter().any(|p| p.expand(LABEL_PADDING).intersects(*r)))
+	}
+
+	// with a halo, so that the text can be read on top of lines
+	fn text(&mut self, pos: Pos2, galley: Arc<Galley>, angle: f32) {
+		for offset in [Vec2::new(-1.0, -1.0), Vec2::new(1.0, -1.0), Vec2::new(-1.0, 1.0), Vec2::new(1.0, 1.0)] {
+			let halo = TextShape::new(pos + offset * LABEL_HALO_WIDTH, galley.clone(), LABEL_HALO_COLOR)
+				.with_override_text_color(LABEL_HALO_COLOR)
+				.with_angle(angle);
+			self.shapes.push(halo.into());
+		}
+		self.shapes.push(TextShape::new(pos, galley, LABEL_COLOR).with_angle(angle).into());
+	}
+}
+
+// the name with the house number under it, so that the two never compete for the same spot
+pub fn text(tags: &Tags, house_numbers: bool) -> Option<String> {
+	let number = tags.get("addr:housenumber").filter(|_| house_numbers);
+	match (tags.get("name"), number) {
+		(Some(name), Some(number)) => Some(format!("{name}\n{number}")),
+		(name, number) => name.or(number).cloned(),
+	}
+}
+
+fn layout(ui: &Ui, text: &str) -> Arc<Galley> {
+	ui.fonts(|f| f.layout_no_wrap(text.to_string(), FontId::proportional(LABEL_SIZE), LABEL_COLOR))
+}
+
+// centers and angles of letters at `offsets` along text `width` wide, centered on the line
+fn place_along(points: &[Pos2], width: f32, offsets: &[f32]) -> Option<Vec<(Pos2, f32)>> {
+	let length: f32 = points.windows(2).map(|w| (w[1] - w[0]).length()).sum();
+	if length < width + LABEL_PADDING * 2.0 { return None; }
+
+	let start = (length - width) / 2.0;
+	let placement: Vec<(Pos2, f32)> = offsets.iter().map(|offset| point_along(points, start + offset)).collect::<Option<_>>()?;
+
+	let bends = placement.windows(2).any(|w| {
+		let turn = (w[1].1 - w[0].1).rem_euclid(2.0 * PI);
+		turn.min(2.0 * PI - turn) > LABEL_MAX_BEND
+	});
+	(!bends).then_some(placement)
+}
+
+// position and direction at a distance from the start of a line
+fn point_along(points: &[Pos2], distance: f32) -> Option<(Pos2, f32)> {
+	let mut remaining = distance;
+
+	for segment in points.windows(2) {
+		let direction = segment[1] - segment[0];
+		let length = direction.length();
+		if length == 0.0 { continue; }
+
+		if remaining <= length {
+			return Some((segment[0] + direction * (remaining / length), direction.angle()));
+		}
+		remaining -= length;
+	}
+
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::test_util::tags;
+
+	#[test]
+	fn letters_follow_the_line() {
+		let corner = [Pos2::new(0.0, 0.0), Pos2::new(100.0, 0.0), Pos2::new(100.0, 100.0)];
+		assert_eq!(point_along(&corner, 50.0), Some((Pos2::new(50.0, 0.0), 0.0)));
+		assert_eq!(point_along(&corner, 150.0), Some((Pos2::new(100.0, 50.0), PI / 2.0)));
+		assert_eq!(point_along(&corner, 250.0), None);
+
+		// centered on the first segment
+		let placement = place_along(&corner[..2], 20.0, &[5.0, 15.0]).unwrap();
+		assert_eq!(placement, [(Pos2::new(45.0, 0.0), 0.0), (Pos2::new(55.0, 0.0), 0.0)]);
+
+		// too short, and around the corner
+		assert!(place_along(&corner[..2], 100.0, &[50.0]).is_none());
+		assert!(place_along(&corner, 20.0, &[5.0, 15.0]).is_none());
+	}
+
+	#[test]
+	fn house_numbers_go_under_names() {
+		let house = tags(&[("name", "Town Hall"), ("addr:housenumber", "12")]);
+		assert_eq!(text(&house, true).as_deref(), Some("Town Hall\n12"));
+		assert_eq!(text(&house, false).as_deref(), Some("Town Hall"));
+		assert_eq!(text(&tags(&[("addr:housenumber", "12")]), true).as_deref(), Some("12"));
+		assert_eq!(text(&tags(&[("addr:housenumber", "12")]), false), None);
+		assert_eq!(text(&tags(&[("building", "yes")]), true), None);
+	}
+}
@@ -0,0 +1,222 @@
+use super::super::document::{ElementRef, Relation};
+use super::detail;
+use super::projection::Projection;
+use eframe::egui::{Pos2, Vec2};
+use osm_parser::{Id, OsmData};
+use std::collections::HashMap;
+
+// rings of a multipolygon relation as node ids, each closed by repeating the first node
+#[derive(Debug, Default, PartialEq)]
+pub struct Multipolygon {
+	pub outer: Vec<Vec<Id>>,
+	pub inner: Vec<Vec<Id>>,
+}
+
+// an outer ring and the inner rings inside it, in screen coordinates
+pub struct Polygon {
+	pub outer: Vec<Pos2>,
+	pub holes: Vec<Vec<Pos2>>,
+}
+
+// assembled rings by relation, kept until the document changes
+#[derive(Default)]
+pub struct MultipolygonCache {
+	revision: Option<u64>,
+	assembled: HashMap<Id, Multipolygon>,
+}
+
+impl MultipolygonCache {
+	pub fn get(&mut self, relation: &Relation, data: &OsmData, revision: u64) -> &Multipolygon {
+		if self.revision != Some(revision) {
+			self.assembled.clear();
+			self.revision = Some(revision);
+		}
+
+		self.assembled.entry(relation.id).or_insert_with(|| assemble(relation, data))
+	}
+}
+
+impl Multipolygon {
+	pub fn project(&self, projection: &Projection) -> Vec<Polygon> {
+		let mut polygons: Vec<Polygon> = self.outer.iter()
+			.map(|ring| Polygon::new(ring, projection))
+			// rings whose nodes are all missing
+			.filter(|p| !p.outer.is_empty())
+			.collect();
+
+		for ring in &self.inner {
+			let hole = projection.nodes(ring);
+			let Some(first) = hole.first().copied() else { continue };
+			if let Some(polygon) = polygons.iter_mut().find(|p| ring_contains(&p.outer, first)) {
+				polygon.holes.push(hole);
+			}
+		}
+
+		polygons
+	}
+}
+
+impl Polygon {
+	// a closed way without holes
+	pub fn new(ring: &[Id], projection: &Projection) -> Self {
+		Polygon { outer: projection.nodes(ring), holes: Vec::new() }
+	}
+
+	pub fn contains(&self, p: Pos2) -> bool {
+		ring_contains(&self.outer, p) && !self.holes.iter().any(|h| ring_contains(h, p))
+	}
+
+	// on screen, holes are not subtracted
+	pub fn area(&self) -> f32 {
+		let twice: f32 = self.outer.windows(2).map(|w| w[0].x * w[1].y - w[1].x * w[0].y).sum();
+		twice.abs() / 2.0
+	}
+
+	// of the outer ring, the average of its points when it has no area
+	pub fn centroid(&self) -> Pos2 {
+		let (mut twice, mut sum) = (0.0, Vec2::ZERO);
+		for w in self.outer.windows(2) {
+			let cross = w[0].x * w[1].y - w[1].x * w[0].y;
+			twice += cross;
+			sum += (w[0].to_vec2() + w[1].to_vec2()) * cross;
+		}
+
+		if twice.abs() > f32::EPSILON {
+			(sum / (3.0 * twice)).to_pos2()
+		} else {
+			let sum = self.outer.iter().fold(Vec2::ZERO, |sum, p| sum + p.to_vec2());
+			(sum / self.outer.len().max(1) as f32).to_pos2()
+		}
+	}
+
+	pub fn simplify(&mut self, tolerance: f32) {
+		self.outer = detail::simplify(&self.outer, tolerance);
+		for hole in &mut self.holes {
+			*hole = detail::simplify(hole, tolerance);
+		}
+	}
+
+	pub fn rings(&self) -> impl Iterator<Item = &[Pos2]> {
+		std::iter::once(self.outer.as_slice()).chain(self.holes.iter().map(Vec::as_slice))
+	}
+}
+
+// even-odd rule, the ring is closed
+fn ring_contains(ring: &[Pos2], p: Pos2) -> bool {
+	let mut inside = false;
+
+	for w in ring.windows(2) {
+		let (a, b) = (w[0], w[1]);
+		if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+			inside = !inside;
+		}
+	}
+
+	inside
+}
+
+pub fn is_multipolygon(relation: &Relation) -> bool {
+	relation.tags.get("type").is_some_and(|t| t == "multipolygon")
+}
+
+// joins the member ways into rings, rings left open by members that are not loaded are dropped
+pub fn assemble(relation: &Relation, data: &OsmData) -> Multipolygon {
+	let mut outer = Vec::new();
+	let mut inner = Vec::new();
+
+	for member in &relation.members {
+		let ElementRef::Way(id) = member.element else { continue };
+		let Some(way) = data.ways.get(&id) else { continue };
+		if !way.nodes.iter().all(|n| data.nodes.contains_key(n)) { continue; }
+
+		match member.role.as_str() {
+			// an empty role is still common for outer ways
+			"outer" | "" => outer.push(way.nodes.clone()),
+			"inner" => inner.push(way.nodes.clone()),
+			_ => {},
+		}
+	}
+
+	Multipolygon { outer: join(outer), inner: join(inner) }
+}
+
+fn join(mut segments: Vec<Vec<Id>>) -> Vec<Vec<Id>> {
+	let mut rings = Vec::new();
+
+	while let Some(mut ring) = segments.pop() {
+		while ring.len() > 1 && ring.first() != ring.last() {
+			let end = ring[ring.len() - 1];
+			let Some(i) = segments.iter().position(|s| s.first() == Some(&end) || s.last() == Some(&end)) else { break };
+
+			let mut next = segments.swap_remove(i);
+			if next[0] != end {
+				next.reverse();
+			}
+			ring.extend_from_slice(&next[1..]);
+		}
+
+		// a triangle is the smallest ring, with the first node repeated
+		if ring.len() >= 4 && ring.first() == ring.last() {
+			rings.push(ring);
+		}
+	}
+
+	rings
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::super::document::Member;
+
+	#[test]
+	fn join_reversed_segments() {
+		let rings = join(vec![vec![1, 2, 3], vec![5, 4, 3], vec![5, 6, 1]]);
+		assert_eq!(rings.len(), 1);
+
+		let ring = &rings[0];
+		assert_eq!(ring.first(), ring.last());
+		let mut nodes = ring[1..].to_vec();
+		nodes.sort();
+		assert_eq!(nodes, [1, 2, 3, 4, 5, 6]);
+	}
+
+	#[test]
+	fn contains_with_hole() {
+		let square = |min: f32, max: f32| vec![
+			Pos2::new(min, min), Pos2::new(max, min), Pos2::new(max, max), Pos2::new(min, max), Pos2::new(min, min),
+		];
+		let polygon = Polygon { outer: square(0.0, 10.0), holes: vec![square(4.0, 6.0)] };
+
+		assert!(polygon.contains(Pos2::new(2.0, 2.0)));
+		assert!(!polygon.contains(Pos2::new(5.0, 5.0)));
+		assert!(!polygon.contains(Pos2::new(12.0, 5.0)));
+		assert_eq!(polygon.area(), 100.0);
+		assert_eq!(polygon.centroid(), Pos2::new(5.0, 5.0));
+	}
+
+	#[test]
+	fn cache_follows_revision() {
+		let mut data = OsmData::default();
+		for id in 1..=3 {
+			data.nodes.insert(id, osm_parser::Node { id, pos: Default::default(), tags: Default::default() });
+		}
+		data.ways.insert(10, osm_parser::Way { id: 10, nodes: vec![1, 2, 3, 1], tags: Default::default() });
+		let member = Member { element: ElementRef::Way(10), role: "outer".to_string() };
+		let relation = Relation { id: 100, members: vec![member], tags: Default::default() };
+
+		let mut cache = MultipolygonCache::default();
+		assert_eq!(cache.get(&relation, &data, 1).outer.len(), 1);
+
+		// not assembled again until the revision changes
+		data.ways.remove(&10);
+		assert_eq!(cache.get(&relation, &data, 1).outer.len(), 1);
+		assert!(cache.get(&relation, &data, 2).outer.is_empty());
+	}
+
+	#[test]
+	fn join_drops_open_rings() {
+		let rings = join(vec![vec![1, 2, 3, 1], vec![10, 11, 12], vec![12, 13]]);
+		assert_eq!(rings, [vec![1, 2, 3, 1]]);
+	}
+}
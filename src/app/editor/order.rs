@@ -0,0 +1,142 @@
+use super::consts::osm::*;
+use super::consts::*;
+use super::style::Style;
+use osm_parser::{Id, Tags};
+use std::cmp::Ordering;
+
+// whether a way is below or above the ground of its layer
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Structure {
+	Tunnel,
+	Ground,
+	Bridge,
+}
+
+impl Structure {
+	pub fn of(tags: &Tags) -> Self {
+		let present = |key: &str| tags.get(key).is_some_and(|v| v != "no");
+		if present("bridge") {
+			Structure::Bridge
+		} else if present("tunnel") {
+			Structure::Tunnel
+		} else {
+			Structure::Ground
+		}
+	}
+}
+
+// position of a line in the drawing order, later lines are drawn on top and hovered first
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DrawOrder {
+	pub layer: i32,
+	pub structure: Structure,
+	z_index: f32,
+	class: u8,
+	// keeps the order the same between frames when everything else is equal
+	id: Id,
+}
+
+impl DrawOrder {
+	pub fn new(id: Id, tags: &Tags, style: &Style) -> Self {
+		Self { layer: layer(tags), structure: Structure::of(tags), z_index: style.z_index, class: road_class(tags), id }
+	}
+
+	// lines on the same level share a casing pass, so that bridges are cased over what is below them
+	pub fn level(&self) -> (i32, Structure) {
+		(self.layer, self.structure)
+	}
+}
+
+impl Eq for DrawOrder {}
+
+impl PartialOrd for DrawOrder {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for DrawOrder {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.level().cmp(&other.level())
+			.then(self.z_index.total_cmp(&other.z_index))
+			.then(self.class.cmp(&other.class))
+			.then(self.id.cmp(&other.id))
+	}
+}
+
+// `layer=*` as a whole number, anything else is on the ground
+pub fn layer(tags: &Tags) -> i32 {
+	tags.get("layer").and_then(|l| l.trim().parse::<f32>().ok()).map_or(0, |l| l.round() as i32)
+}
+
+// tunnels are faded and bridges always have a casing
+pub fn adjust(style: &mut Style, structure: Structure) {
+	match structure {
+		Structure::Tunnel => {
+			style.color = style.color.gamma_multiply(TUNNEL_OPACITY);
+			style.casing_color = style.casing_color.gamma_multiply(TUNNEL_OPACITY);
+		},
+		Structure::Bridge => {
+			style.casing_color = BRIDGE_CASING_COLOR;
+			style.casing_width = style.casing_width.max(BRIDGE_CASING_WIDTH);
+		},
+		Structure::Ground => {},
+	}
+}
+
+// more important roads are drawn over less important ones on the same level
+pub fn road_class(tags: &Tags) -> u8 {
+	match tags.get("highway").map(String::as_str) {
+		Some(MOTORWAY) => 12,
+		Some(TRUNK) => 11,
+		Some(PRIMARY) => 10,
+		Some(SECONDARY) => 9,
+		Some(TERTIARY) => 8,
+		Some(MOTORWAY_LINK | TRUNK_LINK | PRIMARY_LINK | SECONDARY_LINK | TERTIARY_LINK) => 7,
+		Some(UNCLASSIFIED | RESIDENTIAL) => 6,
+		Some(LIVING_STREET) => 5,
+		Some(SERVICE | PEDESTRIAN) => 4,
+		Some(TRACK) => 3,
+		Some(FOOTWAY | CYCLEWAY | BRIDLEWAY | STEPS | PATH) => 2,
+		Some(_) => 1,
+		None => 0,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::test_util::tags;
+
+	fn order(id: Id, pairs: &[(&str, &str)]) -> DrawOrder {
+		DrawOrder::new(id, &tags(pairs), &Style::default())
+	}
+
+	#[test]
+	fn layers_structures_and_classes() {
+		let footway_bridge = order(1, &[("highway", "footway"), ("bridge", "yes")]);
+		let primary = order(2, &[("highway", "primary")]);
+		let residential = order(3, &[("highway", "residential")]);
+		let tunnel = order(4, &[("highway", "motorway"), ("tunnel", "yes")]);
+		let elevated = order(5, &[("highway", "service"), ("layer", "2")]);
+		let not_a_bridge = order(6, &[("highway", "primary"), ("bridge", "no")]);
+
+		let mut ways = [elevated, footway_bridge, residential, primary, tunnel, not_a_bridge];
+		ways.sort();
+		assert_eq!(ways, [tunnel, residential, primary, not_a_bridge, footway_bridge, elevated]);
+
+		assert_eq!(layer(&tags(&[("layer", "-1")])), -1);
+		assert_eq!(layer(&tags(&[("layer", "high")])), 0);
+	}
+
+	#[test]
+	fn bridges_are_cased() {
+		let mut style = Style::default();
+		adjust(&mut style, Structure::Bridge);
+		assert_eq!((style.casing_color, style.casing_width), (BRIDGE_CASING_COLOR, BRIDGE_CASING_WIDTH));
+
+		let mut style = Style::default();
+		adjust(&mut style, Structure::Tunnel);
+		assert!(style.color.a() < DEFAULT_COLOR.a());
+	}
+}
@@ -0,0 +1,59 @@
+use super::coordinate_to_pos;
+use eframe::egui::{Pos2, Vec2};
+use osm_parser::{Id, OsmData};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use walkers::Projector;
+
+// screen positions of nodes, kept between frames until the map is moved or zoomed or the data is edited
+#[derive(Default)]
+pub struct ProjectionCache {
+	// coordinates at two corners of the screen and the document revision the positions were projected for
+	key: Option<([f64; 4], u64)>,
+	positions: RefCell<HashMap<Id, Pos2>>,
+}
+
+impl ProjectionCache {
+	// must be called every frame before the cache is used
+	pub fn update(&mut self, projector: &Projector, revision: u64) {
+		let (a, b) = (projector.unproject(Vec2::ZERO), projector.unproject(Vec2::splat(1000.0)));
+		let key = Some(([a.lon(), a.lat(), b.lon(), b.lat()], revision));
+
+		if key != self.key {
+			self.positions.get_mut().clear();
+			self.key = key;
+		}
+	}
+}
+
+// projects nodes on demand, so only the ones that are drawn or hovered are ever projected
+pub struct Projection<'a> {
+	cache: &'a ProjectionCache,
+	data: &'a OsmData,
+	projector: &'a Projector,
+}
+
+impl<'a> Projection<'a> {
+	pub fn new(cache: &'a ProjectionCache, data: &'a OsmData, projector: &'a Projector) -> Self {
+		Self { cache, data, projector }
+	}
+
+	// None for nodes that are not loaded, e.g. of ways exported without their nodes
+	pub fn node(&self, id: Id) -> Option<Pos2> {
+		self.project(&mut self.cache.positions.borrow_mut(), id)
+	}
+
+	// nodes that are not loaded are left out
+	pub fn nodes(&self, ids: &[Id]) -> Vec<Pos2> {
+		let mut positions = self.cache.positions.borrow_mut();
+		ids.iter().filter_map(|id| self.project(&mut positions, *id)).collect()
+	}
+
+	fn project(&self, positions: &mut HashMap<Id, Pos2>, id: Id) -> Option<Pos2> {
+		if let Some(pos) = positions.get(&id) { return Some(*pos); }
+
+		let pos = self.projector.project(coordinate_to_pos(&self.data.nodes.get(&id)?.pos)).to_pos2();
+		positions.insert(id, pos);
+		Some(pos)
+	}
+}
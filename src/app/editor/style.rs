@@ -0,0 +1,280 @@
+mod mapcss;
+
+use super::consts::osm::DEFAULT_WIDTH;
+use super::consts::{AREA_FILL_OPACITY, DEFAULT_COLOR};
+use eframe::egui::Color32;
+use osm_parser::Tags;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+pub use super::consts::STYLE_RELOAD_INTERVAL;
+pub use mapcss::ParseError;
+
+// used until a stylesheet is loaded, reproduces the original hard-coded look
+const DEFAULT_STYLESHEET: &str = include_str!("style/default.mapcss");
+
+// what rules are matched against, areas are closed ways with area tags and multipolygon relations
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Subject {
+	Node,
+	Way { area: bool },
+	Relation { area: bool },
+}
+
+// the part of a selector before the zoom range and conditions
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Type {
+	Node,
+	Way,
+	Area,
+	Relation,
+	Any,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+	Has(String),
+	HasNot(String),
+	Equals(String, String),
+	NotEquals(String, String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Selector {
+	kind: Type,
+	// from the first zoom level up to the start of the last one, `|z12-16` includes 16.5
+	min_zoom: f64,
+	max_zoom: f64,
+	conditions: Vec<Condition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Property {
+	Color(Color32),
+	Width(f32),
+	Dashes(Vec<f32>),
+	CasingColor(Color32),
+	CasingWidth(f32),
+	FillColor(Color32),
+	FillOpacity(f32),
+	ZIndex(f32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+	selectors: Vec<Selector>,
+	properties: Vec<Property>,
+}
+
+// the properties of every rule matching an object, later rules override earlier ones
+#[derive(Debug, Clone, PartialEq)]
+pub struct Style {
+	pub color: Color32,
+	pub width: f32,
+	// alternating dash and gap lengths, solid when empty
+	pub dashes: Vec<f32>,
+	pub casing_color: Color32,
+	// on each side of the line, no casing when zero
+	pub casing_width: f32,
+	// areas are filled with their line color unless set
+	pub fill_color: Option<Color32>,
+	pub fill_opacity: f32,
+	pub z_index: f32,
+}
+
+impl Default for Style {
+	fn default() -> Self {
+		Self {
+			color: DEFAULT_COLOR,
+			width: DEFAULT_WIDTH,
+			dashes: Vec::new(),
+			casing_color: Color32::BLACK,
+			casing_width: 0.0,
+			fill_color: None,
+			fill_opacity: AREA_FILL_OPACITY,
+			z_index: 0.0,
+		}
+	}
+}
+
+impl Style {
+	pub fn fill(&self) -> Color32 {
+		self.fill_color.unwrap_or(self.color).gamma_multiply(self.fill_opacity)
+	}
+
+	fn apply(&mut self, property: &Property) {
+		match property {
+			Property::Color(c) => self.color = *c,
+			Property::Width(w) => self.width = *w,
+			Property::Dashes(d) => self.dashes = d.clone(),
+			Property::CasingColor(c) => self.casing_color = *c,
+			Property::CasingWidth(w) => self.casing_width = *w,
+			Property::FillColor(c) => self.fill_color = Some(*c),
+			Property::FillOpacity(o) => self.fill_opacity = *o,
+			Property::ZIndex(z) => self.z_index = *z,
+		}
+	}
+}
+
+impl Selector {
+	fn matches(&self, subject: Subject, tags: &Tags, zoom: f64) -> bool {
+		let kind = match (self.kind, subject) {
+			(Type::Any, _) | (Type::Node, Subject::Node) | (Type::Way, Subject::Way { .. }) | (Type::Relation, Subject::Relation { .. }) => true,
+			(Type::Area, Subject::Way { area } | Subject::Relation { area }) => area,
+			_ => false,
+		};
+
+		kind && zoom >= self.min_zoom && zoom < self.max_zoom && self.conditions.iter().all(|c| match c {
+			Condition::Has(k) => tags.contains_key(k),
+			Condition::HasNot(k) => !tags.contains_key(k),
+			Condition::Equals(k, v) => tags.get(k) == Some(v),
+			Condition::NotEquals(k, v) => tags.get(k) != Some(v),
+		})
+	}
+}
+
+#[derive(Debug)]
+pub enum Error {
+	Io(std::io::Error),
+	Parse(ParseError),
+}
+
+impl Display for Error {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Error::Io(e) => write!(f, "could not read file: {e}"),
+			Error::Parse(e) => write!(f, "invalid stylesheet: {e}"),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+	fn from(value: std::io::Error) -> Self { Error::Io(value) }
+}
+
+impl From<ParseError> for Error {
+	fn from(value: ParseError) -> Self { Error::Parse(value) }
+}
+
+pub struct StyleSheet {
+	rules: Vec<Rule>,
+	// file the rules were read from, watched for changes
+	source: Option<Source>,
+}
+
+struct Source {
+	path: PathBuf,
+	modified: Option<SystemTime>,
+	checked: Instant,
+}
+
+impl Default for StyleSheet {
+	fn default() -> Self {
+		Self { rules: mapcss::parse(DEFAULT_STYLESHEET).expect("default stylesheet is valid"), source: None }
+	}
+}
+
+impl StyleSheet {
+	pub fn load(path: &Path) -> Result<Self, Error> {
+		let modified = std::fs::metadata(path)?.modified().ok();
+		let rules = mapcss::parse(&std::fs::read_to_string(path)?)?;
+
+		Ok(Self { rules, source: Some(Source { path: path.to_path_buf(), modified, checked: Instant::now() }) })
+	}
+
+	pub fn path(&self) -> Option<&Path> {
+		self.source.as_ref().map(|s| s.path.as_path())
+	}
+
+	// rereads the file when it changed, checked at most every STYLE_RELOAD_INTERVAL,
+	// an invalid file keeps the previous rules so that a typo does not make everything disappear
+	pub fn reload(&mut self) -> Option<Result<(), Error>> {
+		let source = self.source.as_mut()?;
+		if source.checked.elapsed() < STYLE_RELOAD_INTERVAL { return None; }
+		source.checked = Instant::now();
+
+		let modified = std::fs::metadata(&source.path).and_then(|m| m.modified()).ok();
+		if modified == source.modified { return None; }
+		source.modified = modified;
+
+		let result = std::fs::read_to_string(&source.path).map_err(Error::from)
+			.and_then(|text| Ok(mapcss::parse(&text)?));
+		Some(result.map(|rules| self.rules = rules))
+	}
+
+	pub fn style(&self, subject: Subject, tags: &Tags, zoom: f64) -> Style {
+		let mut style = Style::default();
+
+		for rule in self.rules.iter().filter(|r| r.selectors.iter().any(|s| s.matches(subject, tags, zoom))) {
+			for property in &rule.properties {
+				style.apply(property);
+			}
+		}
+
+		style
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::test_util::tags;
+
+	#[test]
+	fn default_look() {
+		let style = StyleSheet::default();
+		let way = Subject::Way { area: false };
+
+		let residential = style.style(way, &tags(&[("highway", "residential")]), 18.0);
+		assert_eq!((residential.color, residential.width), (Color32::WHITE, 5.0));
+
+		let steps = style.style(way, &tags(&[("highway", "steps")]), 18.0);
+		assert_eq!((steps.color, steps.width), (Color32::from_rgb(129, 210, 92), 2.5));
+		assert_eq!(steps.dashes, [1.5, 1.0]);
+
+		// paved tracks are solid and roads are cased
+		let track = style.style(way, &tags(&[("highway", "track"), ("tracktype", "grade1")]), 18.0);
+		assert!(track.dashes.is_empty());
+		assert!(residential.casing_width > 0.0);
+
+		// the building key takes precedence, even when it says there is none
+		let not_building = style.style(way, &tags(&[("highway", "service"), ("building", "no")]), 18.0);
+		assert_eq!((not_building.color, not_building.width), (DEFAULT_COLOR, DEFAULT_WIDTH));
+
+		let park = style.style(Subject::Relation { area: true }, &tags(&[("leisure", "park"), ("landuse", "grass")]), 18.0);
+		assert_eq!(park.fill(), Color32::from_rgb(140, 200, 110).gamma_multiply(AREA_FILL_OPACITY));
+
+		let entrance = style.style(Subject::Node, &tags(&[("entrance", "main"), ("barrier", "door")]), 18.0);
+		assert_eq!(entrance.color, Color32::from_rgb(230, 140, 40));
+	}
+
+	#[test]
+	fn reload_changed_file() {
+		let path = std::env::temp_dir().join(format!("walkers-editor-style-{}.mapcss", std::process::id()));
+		std::fs::write(&path, "way { width: 3; }").unwrap();
+		let mut style = StyleSheet::load(&path).unwrap();
+		assert_eq!(style.style(Subject::Way { area: false }, &Tags::new(), 18.0).width, 3.0);
+
+		// the modification time may not have changed within the resolution of the file system
+		let rewrite = |style: &mut StyleSheet, text: &str| {
+			std::fs::write(&path, text).unwrap();
+			let source = style.source.as_mut().unwrap();
+			source.modified = None;
+			source.checked -= STYLE_RELOAD_INTERVAL;
+			style.reload()
+		};
+
+		assert!(matches!(rewrite(&mut style, "way { width: 4; }"), Some(Ok(()))));
+		assert_eq!(style.style(Subject::Way { area: false }, &Tags::new(), 18.0).width, 4.0);
+
+		// a broken file keeps the rules that worked
+		assert!(matches!(rewrite(&mut style, "way { width: 5 "), Some(Err(Error::Parse(_)))));
+		assert_eq!(style.style(Subject::Way { area: false }, &Tags::new(), 18.0).width, 4.0);
+
+		assert!(style.reload().is_none());
+		std::fs::remove_file(&path).unwrap();
+	}
+}
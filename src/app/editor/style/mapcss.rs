@@ -0,0 +1,389 @@
+use super::{Condition, Property, Rule, Selector, Type};
+use eframe::egui::Color32;
+use std::fmt::{Display, Formatter};
+
+// the supported subset: `node`, `way`, `area`, `relation` and `*` selectors with a zoom range like `|z12-16`
+// and tag conditions `[k]`, `[!k]`, `[k=v]`, `[k!=v]`, other selectors like `meta`, `canvas`, `way:closed`
+// or `way::casing`, properties of other renderers and `eval` expressions are skipped
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+	pub line: usize,
+	pub message: String,
+}
+
+impl Display for ParseError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
+
+impl std::error::Error for ParseError {}
+
+pub fn parse(text: &str) -> Result<Vec<Rule>, ParseError> {
+	let mut parser = Parser { chars: text.chars().collect(), pos: 0, line: 1 };
+	let mut rules = Vec::new();
+
+	while parser.skip_whitespace() {
+		if let Some(rule) = parser.rule()? {
+			rules.push(rule);
+		}
+	}
+
+	Ok(rules)
+}
+
+struct Parser {
+	chars: Vec<char>,
+	pos: usize,
+	line: usize,
+}
+
+impl Parser {
+	// None when none of the selectors is supported
+	fn rule(&mut self) -> Result<Option<Rule>, ParseError> {
+		let mut selectors: Vec<Selector> = self.selector()?.into_iter().collect();
+		while self.eat(',') {
+			selectors.extend(self.selector()?);
+		}
+
+		self.expect('{')?;
+		let mut properties = Vec::new();
+
+		loop {
+			if self.eat('}') { break; }
+			let name = self.word("-")?;
+			self.expect(':')?;
+			let value = self.declaration_value();
+			self.eat(';');
+
+			if let Some(property) = property(&name, value.trim()).map_err(|message| self.error(message))? {
+				properties.push(property);
+			}
+		}
+
+		Ok((!selectors.is_empty()).then_some(Rule { selectors, properties }))
+	}
+
+	// None for types that are not supported, the rest of the selector is still read
+	fn selector(&mut self) -> Result<Option<Selector>, ParseError> {
+		self.skip_whitespace();
+		let kind = if self.eat('*') {
+			Some(Type::Any)
+		} else {
+			match self.word("")?.as_str() {
+				"node" => Some(Type::Node),
+				"way" => Some(Type::Way),
+				"area" => Some(Type::Area),
+				"relation" => Some(Type::Relation),
+				_ => None,
+			}
+		};
+
+		// no whitespace between the type and its zoom range or conditions
+		let (mut min_zoom, mut max_zoom) = (0.0, f64::INFINITY);
+		if self.eat_str("|z") {
+			let min = self.number();
+			let range = self.eat('-');
+			let max = if range { self.number() } else { min };
+			if min.is_none() && max.is_none() {
+				return Err(self.error("zoom range without a zoom level".to_string()));
+			}
+
+			min_zoom = min.unwrap_or(0.0);
+			max_zoom = max.map_or(f64::INFINITY, |z| z + 1.0);
+		}
+
+		// pseudo classes like `:closed` and layers other than the default one are not supported either
+		let mut conditions = Vec::new();
+		let mut supported = kind.is_some();
+		loop {
+			if self.eat('[') {
+				conditions.push(self.condition()?);
+				self.expect(']')?;
+			} else if self.eat_str("::") {
+				supported &= self.word("_-")? == "default";
+			} else if self.eat_str(":") {
+				self.word("_-")?;
+				supported = false;
+			} else {
+				break;
+			}
+		}
+
+		Ok(kind.filter(|_| supported).map(|kind| Selector { kind, min_zoom, max_zoom, conditions }))
+	}
+
+	fn condition(&mut self) -> Result<Condition, ParseError> {
+		self.skip_whitespace();
+		if self.eat('!') {
+			return Ok(Condition::HasNot(self.value()?));
+		}
+
+		let key = self.value()?;
+		self.skip_whitespace();
+		if self.eat_str("!=") {
+			Ok(Condition::NotEquals(key, self.value()?))
+		} else if self.eat('=') {
+			Ok(Condition::Equals(key, self.value()?))
+		} else {
+			Ok(Condition::Has(key))
+		}
+	}
+
+	// a tag key or value, quoted when it contains other characters
+	fn value(&mut self) -> Result<String, ParseError> {
+		self.skip_whitespace();
+		if !self.eat('"') {
+			return self.word("_-:.");
+		}
+
+		let value = self.until(&['"']);
+		self.expect('"')?;
+		Ok(value)
+	}
+
+	// letters and digits, and `punctuation`
+	fn word(&mut self, punctuation: &str) -> Result<String, ParseError> {
+		self.skip_whitespace();
+		let start = self.pos;
+		while self.peek().is_some_and(|c| c.is_alphanumeric() || punctuation.contains(c)) {
+			self.pos += 1;
+		}
+
+		if start == self.pos {
+			return Err(self.error(match self.peek() {
+				Some(c) => format!("unexpected `{c}`"),
+				None => "unexpected end of file".to_string(),
+			}));
+		}
+		Ok(self.chars[start..self.pos].iter().collect())
+	}
+
+	fn number(&mut self) -> Option<f64> {
+		let start = self.pos;
+		while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+			self.pos += 1;
+		}
+		self.chars[start..self.pos].iter().collect::<String>().parse().ok()
+	}
+
+	fn until(&mut self, ends: &[char]) -> String {
+		let start = self.pos;
+		while let Some(c) = self.peek().filter(|c| !ends.contains(c)) {
+			if c == '\n' { self.line += 1; }
+			self.pos += 1;
+		}
+		self.chars[start..self.pos].iter().collect()
+	}
+
+	// up to the end of a property, quoted strings like the ones in `meta` may contain `;` and `}`
+	fn declaration_value(&mut self) -> String {
+		let start = self.pos;
+		let mut quoted = false;
+		while let Some(c) = self.peek().filter(|c| quoted || !matches!(c, ';' | '}')) {
+			if c == '"' { quoted = !quoted; }
+			if c == '\n' { self.line += 1; }
+			self.pos += 1;
+		}
+		self.chars[start..self.pos].iter().collect()
+	}
+
+	// skips whitespace and comments, returns whether anything is left
+	fn skip_whitespace(&mut self) -> bool {
+		loop {
+			match self.peek() {
+				Some('\n') => { self.line += 1; self.pos += 1; },
+				Some(c) if c.is_whitespace() => self.pos += 1,
+				Some('/') if self.chars.get(self.pos + 1) == Some(&'/') => { self.until(&['\n']); },
+				Some('/') if self.chars.get(self.pos + 1) == Some(&'*') => {
+					self.pos += 2;
+					while self.peek().is_some() && !self.eat_str("*/") {
+						if self.peek() == Some('\n') { self.line += 1; }
+						self.pos += 1;
+					}
+				},
+				Some(_) => return true,
+				None => return false,
+			}
+		}
+	}
+
+	fn peek(&self) -> Option<char> {
+		self.chars.get(self.pos).copied()
+	}
+
+	fn eat(&mut self, c: char) -> bool {
+		self.skip_whitespace();
+		let found = self.peek() == Some(c);
+		if found { self.pos += 1; }
+		found
+	}
+
+	// without skipping whitespace first
+	fn eat_str(&mut self, s: &str) -> bool {
+		let found = s.chars().enumerate().all(|(i, c)| self.chars.get(self.pos + i) == Some(&c));
+		if found { self.pos += s.chars().count(); }
+		found
+	}
+
+	fn expect(&mut self, c: char) -> Result<(), ParseError> {
+		if self.eat(c) { return Ok(()); }
+		Err(self.error(match self.peek() {
+			Some(found) => format!("expected `{c}` but found `{found}`"),
+			None => format!("expected `{c}` but the file ended"),
+		}))
+	}
+
+	fn error(&self, message: String) -> ParseError {
+		ParseError { line: self.line, message }
+	}
+}
+
+// None for properties and values that are not supported
+fn property(name: &str, value: &str) -> Result<Option<Property>, String> {
+	if value.starts_with("eval(") { return Ok(None); }
+
+	let property = match name {
+		"color" => Property::Color(color(value)?),
+		"width" => Property::Width(number(value)?),
+		"dashes" => Property::Dashes(match value {
+			"none" => Vec::new(),
+			_ => value.split(',').map(|d| dash(d.trim())).collect::<Result<_, _>>()?,
+		}),
+		"casing-color" => Property::CasingColor(color(value)?),
+		"casing-width" => Property::CasingWidth(number(value)?),
+		"fill-color" => Property::FillColor(color(value)?),
+		"fill-opacity" => Property::FillOpacity(number(value)?),
+		"z-index" => Property::ZIndex(number(value)?),
+		_ => return Ok(None),
+	};
+	Ok(Some(property))
+}
+
+fn number(value: &str) -> Result<f32, String> {
+	value.parse().map_err(|_| format!("`{value}` is not a number"))
+}
+
+// a dash or gap that is not longer than zero would never end
+fn dash(value: &str) -> Result<f32, String> {
+	let length = number(value)?;
+	if length > 0.0 { Ok(length) } else { Err(format!("dash length `{value}` is not positive")) }
+}
+
+// #rgb, #rrggbb, #rrggbbaa or one of the CSS 2.1 color names
+fn color(value: &str) -> Result<Color32, String> {
+	let invalid = || format!("`{value}` is not a color");
+	if let Some(color) = named_color(&value.to_ascii_lowercase()) {
+		return Ok(color);
+	}
+
+	let hex = value.strip_prefix('#').filter(|h| h.chars().all(|c| c.is_ascii_hexdigit())).ok_or_else(invalid)?;
+	let digit = |i: usize, len: usize| u8::from_str_radix(&hex[i..i + len], 16).map_err(|_| invalid());
+
+	match hex.len() {
+		3 => Ok(Color32::from_rgb(digit(0, 1)? * 17, digit(1, 1)? * 17, digit(2, 1)? * 17)),
+		6 => Ok(Color32::from_rgb(digit(0, 2)?, digit(2, 2)?, digit(4, 2)?)),
+		8 => Ok(Color32::from_rgba_unmultiplied(digit(0, 2)?, digit(2, 2)?, digit(4, 2)?, digit(6, 2)?)),
+		_ => Err(invalid()),
+	}
+}
+
+fn named_color(name: &str) -> Option<Color32> {
+	let (r, g, b) = match name {
+		"black" => (0, 0, 0),
+		"silver" => (192, 192, 192),
+		"gray" | "grey" => (128, 128, 128),
+		"white" => (255, 255, 255),
+		"maroon" => (128, 0, 0),
+		"red" => (255, 0, 0),
+		"purple" => (128, 0, 128),
+		"fuchsia" | "magenta" => (255, 0, 255),
+		"green" => (0, 128, 0),
+		"lime" => (0, 255, 0),
+		"olive" => (128, 128, 0),
+		"yellow" => (255, 255, 0),
+		"navy" => (0, 0, 128),
+		"blue" => (0, 0, 255),
+		"teal" => (0, 128, 128),
+		"aqua" | "cyan" => (0, 255, 255),
+		"orange" => (255, 165, 0),
+		_ => return None,
+	};
+	Some(Color32::from_rgb(r, g, b))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn selectors_and_properties() {
+		let rules = parse("
+			/* comment */
+			way|z15-[highway=footway][!foot], area[\"addr:street\"!=Main]
+			{
+				color: #fff; // trailing comment
+				dashes: 4, 2;
+				text: name;
+				z-index: -1
+			}
+		").unwrap();
+
+		assert_eq!(rules, [Rule {
+			selectors: vec![
+				Selector {
+					kind: Type::Way,
+					min_zoom: 15.0,
+					max_zoom: f64::INFINITY,
+					conditions: vec![Condition::Equals("highway".into(), "footway".into()), Condition::HasNot("foot".into())],
+				},
+				Selector {
+					kind: Type::Area,
+					min_zoom: 0.0,
+					max_zoom: f64::INFINITY,
+					conditions: vec![Condition::NotEquals("addr:street".into(), "Main".into())],
+				},
+			],
+			properties: vec![Property::Color(Color32::WHITE), Property::Dashes(vec![4.0, 2.0]), Property::ZIndex(-1.0)],
+		}]);
+	}
+
+	#[test]
+	fn zoom_ranges() {
+		let zoom = |text: &str| {
+			let selector = &parse(text).unwrap()[0].selectors[0];
+			(selector.min_zoom, selector.max_zoom)
+		};
+
+		assert_eq!(zoom("node|z12-16 {}"), (12.0, 17.0));
+		assert_eq!(zoom("node|z-14 {}"), (0.0, 15.0));
+		assert_eq!(zoom("node|z17 {}"), (17.0, 18.0));
+	}
+
+	#[test]
+	fn josm_blocks_and_named_colors() {
+		let rules = parse("
+			meta { title: \"Streets; and } paths\"; version: \"1\"; }
+			canvas { fill-color: #f2efe9; }
+			way[highway], way::casing, area:closed { color: Red; width: eval(prop(width) + 2); }
+		").unwrap();
+
+		assert_eq!(rules.len(), 1);
+		assert_eq!(rules[0].selectors.len(), 1);
+		assert_eq!(rules[0].properties, [Property::Color(Color32::from_rgb(255, 0, 0))]);
+	}
+
+	#[test]
+	fn errors_have_lines() {
+		let error = parse("way {\n\tcolor: reddish;\n}").unwrap_err();
+		assert_eq!(error, ParseError { line: 2, message: "`reddish` is not a color".into() });
+
+		let error = parse("way {\n\tdashes: 4,\n\t\t0;\n}").unwrap_err();
+		assert_eq!(error, ParseError { line: 3, message: "dash length `0` is not positive".into() });
+		assert!(parse("way { dashes: -1; }").is_err());
+
+		let error = parse("way[highway\n{}").unwrap_err();
+		assert_eq!(error.line, 2);
+		assert!(parse("way { color: #ggg; }").is_err());
+	}
+}
@@ -0,0 +1,6 @@
+use osm_parser::Tags;
+
+// tags written as literal pairs
+pub fn tags(pairs: &[(&str, &str)]) -> Tags {
+	pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
@@ -1,16 +1,22 @@
-use super::attribute2d::Attribute2D;
+use super::attribute2d::{Attribute2D, SideKey, SubAttribute2D, TagValue, CYCLEWAY, PARKING, SIDEWALK};
 use super::consts::osm::*;
 use super::consts::*;
+use super::multipolygon::Polygon;
+use super::style::Style;
 use eframe::egui;
 use eframe::epaint::PathStroke;
-use egui::{Color32, Pos2, Shape, Window};
-use osm_parser::Way;
+use egui::{Align2, Button, Color32, ComboBox, Grid, Mesh, Pos2, RichText, Shape, Stroke, Vec2, Window};
+use osm_parser::{Node, OsmData, Tags, Way};
+use std::collections::HashMap;
 
 #[derive(Debug, Default, Clone, Copy)]
 #[derive(PartialEq)]
 pub enum Visualization {
 	#[default] Default,
 	Sidewalks,
+	Cycleways,
+	Parking,
+	Surface,
 }
 
 pub const HIGHWAYS_WITH_SIDEWALK: &[&str; 15] = &[
@@ -19,101 +25,784 @@ pub const HIGHWAYS_WITH_SIDEWALK: &[&str; 15] = &[
 	MOTORWAY_LINK, TRUNK_LINK, PRIMARY_LINK, SECONDARY_LINK, TERTIARY_LINK,
 ];
 
-pub fn determine_width_default(w: &Way) -> f32 {
-	if let Some(building) = w.tags.get("building") {
-		return match building.as_str() {
-			"no" => DEFAULT_WIDTH,
-			_ => BUILDING_WIDTH,
-		}
-	} else if let Some(highway) = w.tags.get("highway") {
-		return match highway.as_str() {
-			"path" | "footway" | "steps" => PATH_WIDTH,
-			"service" | "track" => SERVICE_ROAD_WIDTH,
-			"residential" => MINOR_ROAD_WIDTH,
-			"tertiary" | "secondary" | "primary" | "trunk" | "motorway" |
-			"tertiary_link" | "secondary_link" | "primary_link" | "trunk_link" | "motorway_link" => MAJOR_ROAD_WIDTH,
-			_ => DEFAULT_WIDTH,
-		}
-	} else { DEFAULT_WIDTH }
+pub const HIGHWAYS_WITH_CYCLEWAY: &[&str; 12] = &[
+	UNCLASSIFIED, RESIDENTIAL, LIVING_STREET, SERVICE,
+	TRUNK, PRIMARY, SECONDARY, TERTIARY,
+	TRUNK_LINK, PRIMARY_LINK, SECONDARY_LINK, TERTIARY_LINK,
+];
+
+pub const HIGHWAYS_WITH_PARKING: &[&str; 7] = &[
+	UNCLASSIFIED, RESIDENTIAL, LIVING_STREET, SERVICE,
+	PRIMARY, SECONDARY, TERTIARY,
+];
+
+pub const PARKING_ORIENTATIONS: &[&str; 3] = &["parallel", "diagonal", "perpendicular"];
+pub const PARKING_RESTRICTIONS: &[&str; 7] = &[
+	"free", "no_stopping", "no_parking", "no_standing",
+	"loading_only", "charging_only", "disabled",
+];
+
+pub const SURFACES: &[&str; 15] = &[
+	"asphalt", "concrete", "paving_stones", "sett", "cobblestone",
+	"compacted", "fine_gravel", "gravel", "pebblestone", "ground",
+	"dirt", "earth", "grass", "sand", "wood",
+];
+pub const SMOOTHNESSES: &[&str; 8] = &[
+	"excellent", "good", "intermediate", "bad",
+	"very_bad", "horrible", "very_horrible", "impassable",
+];
+
+// closed ways are areas when their tags say so, e.g. a closed highway is still a line unless it has area=yes
+pub fn is_area(way: &Way) -> bool {
+	let closed = way.nodes.len() >= 4 && way.nodes.first() == way.nodes.last();
+	let tags = &way.tags;
+
+	closed && match tags.get("area").map(String::as_str) {
+		Some("yes") => true,
+		Some("no") => false,
+		_ => tags.get("building").is_some_and(|b| b != "no")
+			|| ["landuse", "leisure", "natural", "amenity"].iter().any(|k| tags.contains_key(*k)),
+	}
+}
+
+// nodes with tags other than bookkeeping ones are drawn on their own
+pub fn is_poi(node: &Node) -> bool {
+	node.tags.keys().any(|k| k != "created_by" && k != "source")
 }
 
-pub fn determine_color_default(w: &Way) -> Color32 {
-	if let Some(building) = w.tags.get("building") {
-		return match building.as_str() {
-			"no" => DEFAULT_COLOR,
-			_ => BUILDING_COLOR,
+pub fn node(pos: Pos2, color: Color32, radius: f32) -> Vec<Shape> {
+	vec![
+		Shape::circle_filled(pos, radius, color),
+		Shape::circle_stroke(pos, radius, Stroke::new(1.0, NODE_OUTLINE_COLOR)),
+	]
+}
+
+// ring around a hovered or selected node
+pub fn node_highlight(pos: Pos2, color: Color32, radius: f32) -> Shape {
+	Shape::circle_stroke(pos, radius + 1.0, Stroke::new(2.0, color))
+}
+
+pub fn area(polygon: &Polygon, fill_color: Color32, color: Color32, width: f32) -> Vec<Shape> {
+	let mut shapes = vec![fill(polygon, fill_color)];
+	shapes.extend(outline(polygon, color, width));
+	shapes
+}
+
+pub fn outline(polygon: &Polygon, color: Color32, width: f32) -> Vec<Shape> {
+	// rings repeat their first point, closed_line adds the closing segment itself
+	polygon.rings()
+		.map(|ring| Shape::closed_line(ring[..ring.len().saturating_sub(1)].to_vec(), PathStroke::new(width, color)))
+		.collect()
+}
+
+// egui only fills convex shapes, so the polygon is triangulated
+fn fill(polygon: &Polygon, color: Color32) -> Shape {
+	let mut vertices = Vec::new();
+	let mut holes = Vec::new();
+
+	for (i, ring) in polygon.rings().enumerate() {
+		if i > 0 {
+			holes.push(vertices.len() / 2);
 		}
-	} else if let Some(highway) = w.tags.get("highway") {
-		return match highway.as_str() {
-			"path" => PATH_COLOR,
-			"footway" => FOOTWAY_COLOR,
-			"steps" => STEPS_COLOR,
-			"track" => TRACK_COLOR,
-			_ => Color32::WHITE,
+		vertices.extend(ring[..ring.len() - 1].iter().flat_map(|p| [p.x, p.y]));
+	}
+
+	let mut mesh = Mesh::default();
+	if let Ok(triangles) = earcutr::earcut(&vertices, &holes, 2) {
+		for v in vertices.chunks_exact(2) {
+			mesh.colored_vertex(Pos2::new(v[0], v[1]), color);
 		}
-	} else { DEFAULT_COLOR }
+		for t in triangles.chunks_exact(3) {
+			mesh.add_triangle(t[0] as u32, t[1] as u32, t[2] as u32);
+		}
+	}
+
+	Shape::mesh(mesh)
 }
 
+pub fn default(points: &[Pos2], color: Color32, width: f32) -> Vec<Shape> {
+	vec![Shape::line(points.to_vec(), PathStroke::new(width, color))]
+}
 
-pub fn default(points: [Pos2; 2], color: Color32, width: f32) -> Vec<Shape> {
-	vec![Shape::LineSegment {
-		points,
-		stroke: PathStroke::new(width, color),
-	}]
+// outline drawn below all lines so that the casings of joining roads do not cut through each other,
+// all sizes are multiplied by `scale`
+pub fn casing(points: &[Pos2], style: &Style, scale: f32) -> Option<Shape> {
+	(style.casing_width > 0.0).then(|| {
+		Shape::line(points.to_vec(), PathStroke::new((style.width + style.casing_width * 2.0) * scale, style.casing_color))
+	})
 }
 
-pub fn sidewalks(way: &Way, points: [Pos2; 2], color: Color32, width: f32) -> Vec<Shape> {
-	let mut shapes = Vec::with_capacity(3);
+// solid or dashed line with arrows for one-way roads
+pub fn line(way: &Way, points: &[Pos2], style: &Style, scale: f32) -> Vec<Shape> {
+	let width = style.width * scale;
+	let mut shapes = Vec::with_capacity(1);
 
-	shapes.push(Shape::LineSegment {
-		points,
-		stroke: PathStroke::new(width, color),
-	});
+	if style.dashes.is_empty() {
+		shapes.push(Shape::line(points.to_vec(), PathStroke::new(width, style.color)));
+	} else {
+		// an odd number of lengths is repeated, like in svg
+		let lengths = if style.dashes.len() % 2 == 1 { style.dashes.repeat(2) } else { style.dashes.clone() };
+		let dashes: Vec<f32> = lengths.iter().step_by(2).map(|d| d * scale).collect();
+		let gaps: Vec<f32> = lengths.iter().skip(1).step_by(2).map(|g| g * scale).collect();
+		shapes.extend(Shape::dashed_line_with_offset(points, Stroke::new(width, style.color), &dashes, &gaps, 0.0));
+	}
 
+	if let Some(direction) = oneway(&way.tags) {
+		shapes.extend(oneway_arrows(points, direction, width));
+	}
+
+	shapes
+}
+
+// 1 along the way, -1 against it, None when both directions are allowed
+pub fn oneway(tags: &Tags) -> Option<f32> {
+	match tags.get("oneway").map(String::as_str) {
+		Some("yes" | "true" | "1") => Some(1.0),
+		Some("-1" | "reverse") => Some(-1.0),
+		Some(_) => None,
+		// roundabouts are one-way without saying so
+		None => tags.get("junction").is_some_and(|j| j == "roundabout" || j == "circular").then_some(1.0),
+	}
+}
+
+// triangles pointing in the direction of travel, spacing continues across joints
+fn oneway_arrows(points: &[Pos2], direction: f32, width: f32) -> Vec<Shape> {
+	let size = (width * ONEWAY_ARROW_SIZE).max(ONEWAY_ARROW_MIN_SIZE);
+	let mut shapes = Vec::new();
+	// distance along the current segment to the next arrow
+	let mut next = ONEWAY_ARROW_SPACING / 2.0;
+
+	for segment in points.windows(2) {
+		let (from, to) = (segment[0], segment[1]);
+		let length = (to - from).length();
+		if length == 0.0 { continue; }
+
+		let along = (to - from) / length;
+		let forward = along * direction * size;
+		let side = forward.rot90();
+
+		while next <= length {
+			let center = from + along * next;
+			shapes.push(Shape::convex_polygon(
+				vec![center + forward, center - forward + side, center - forward - side],
+				ONEWAY_ARROW_COLOR,
+				Stroke::NONE,
+			));
+			next += ONEWAY_ARROW_SPACING;
+		}
+		next -= length;
+	}
+
+	shapes
+}
+
+pub fn sidewalks(way: &Way, points: &[Pos2], color: Color32, width: f32) -> Vec<Shape> {
+	let mut shapes = Vec::with_capacity(3);
+
+	shapes.push(Shape::line(points.to_vec(), PathStroke::new(width, color)));
 
 	if way.tags.keys().any(|k| k.starts_with("sidewalk")) {
 		if !sidewalks_relevant(&way.tags) { return shapes; };
-		let attr = Attribute2D::new(&way.tags, "sidewalk");
+		let attr = Attribute2D::new(&way.tags, &SIDEWALK);
 
-		let from = points[0];
-		let to = points[1];
+		shapes.extend(side_lines(points, width, sidewalk_color(attr.left), sidewalk_color(attr.right)));
+	}
 
-		let orthogonal = (to - from).normalized().rot90();
-		let offset = orthogonal * width;
+	shapes
+}
 
-		shapes.push(Shape::LineSegment {
-			points: [from + offset, to + offset],
-			stroke: PathStroke::new(width, attr.left),
-		});
-		shapes.push(Shape::LineSegment {
-			points: [from - offset, to - offset],
-			stroke: PathStroke::new(width, attr.right),
-		});
+pub fn cycleways(way: &Way, points: &[Pos2], color: Color32, width: f32) -> Vec<Shape> {
+	let mut shapes = Vec::with_capacity(3);
 
-		return shapes;
+	shapes.push(Shape::line(points.to_vec(), PathStroke::new(width, color)));
+
+	if way.tags.keys().any(|k| k.starts_with("cycleway")) {
+		if !cycleways_relevant(&way.tags) { return shapes; };
+		let attr = Attribute2D::new(&way.tags, &CYCLEWAY);
+
+		shapes.extend(side_lines(points, width, cycleway_color(attr.left), cycleway_color(attr.right)));
 	}
 
+	shapes
+}
+
+pub fn parking(way: &Way, points: &[Pos2], color: Color32, width: f32) -> Vec<Shape> {
+	let mut shapes = Vec::with_capacity(3);
+
+	shapes.push(Shape::line(points.to_vec(), PathStroke::new(width, color)));
+
+	if way.tags.keys().any(|k| k.starts_with("parking")) {
+		if !parking_relevant(&way.tags) { return shapes; };
+		let attr = Attribute2D::new(&way.tags, &PARKING);
+		let orientation = SubAttribute2D::new(&way.tags, &PARKING, "orientation");
+		let restriction = SubAttribute2D::new(&way.tags, &PARKING, "restriction");
+
+		shapes.extend(side_lines(points, width, parking_color(attr.left), parking_color(attr.right)));
+
+		let sides = [
+			(1.0, attr.left, orientation.left, restriction.left),
+			(-1.0, attr.right, orientation.right, restriction.right),
+		];
+
+		for (side, position, orientation, restriction) in sides {
+			if matches!(position, TagValue::No | TagValue::Separate | TagValue::Unknown) { continue; }
+
+			if let Some(orientation) = orientation {
+				shapes.extend(orientation_ticks(points, width, side, &orientation, parking_color(position)));
+			}
+
+			// thin stripe on top of the parking lane
+			if let Some(color) = restriction.as_deref().and_then(restriction_color) {
+				shapes.push(Shape::line(
+					offset_polyline(points, width * side),
+					PathStroke::new(width / 2.0, color),
+				));
+			}
+		}
+	}
 
 	shapes
 }
 
+// parallel polyline `distance` to the left, negative distances are to the right,
+// joints are mitered so that the offset segments meet instead of breaking apart
+fn offset_polyline(points: &[Pos2], distance: f32) -> Vec<Pos2> {
+	let mut points = points.to_vec();
+	points.dedup();
+	if points.len() < 2 { return points; }
+
+	let closed = points.len() > 2 && points.first() == points.last();
+	let normals: Vec<Vec2> = points.windows(2).map(|s| (s[1] - s[0]).normalized().rot90()).collect();
+	let last = points.len() - 1;
+
+	(0..points.len())
+		.map(|i| {
+			let before = if i > 0 { Some(normals[i - 1]) } else if closed { normals.last().copied() } else { None };
+			let after = if i < last { Some(normals[i]) } else if closed { Some(normals[0]) } else { None };
+
+			let offset = match (before, after) {
+				(Some(a), Some(b)) => miter(a, b) * distance,
+				(Some(n), None) | (None, Some(n)) => n * distance,
+				(None, None) => Vec2::ZERO,
+			};
+			points[i] + offset
+		})
+		.collect()
+}
+
+// offset of a joint between segments with the normals `a` and `b`, per unit of distance
+fn miter(a: Vec2, b: Vec2) -> Vec2 {
+	let sum = a + b;
+	// the way turns back on itself
+	if sum.length_sq() < 1e-6 { return a; }
+
+	let direction = sum.normalized();
+	// sharp angles are cut off instead of producing long spikes
+	direction / direction.dot(a).max(1.0 / MITER_LIMIT)
+}
+
+// ticks on the outside of a parking lane showing how vehicles are parked, parallel parking has none
+fn orientation_ticks(points: &[Pos2], width: f32, side: f32, orientation: &str, color: Color32) -> Vec<Shape> {
+	let spacing = PARKING_TICK_SPACING * width;
+	let mut shapes = Vec::new();
+	// distance along the current segment to the next tick, spacing continues across joints
+	let mut next = spacing;
+
+	for segment in points.windows(2) {
+		let (from, to) = (segment[0], segment[1]);
+		let length = (to - from).length();
+		if length == 0.0 { continue; }
+
+		let direction = (to - from) / length;
+		let outward = direction.rot90() * side;
+		let tick = match orientation {
+			"perpendicular" => outward,
+			"diagonal" => (outward + direction).normalized(),
+			_ => return Vec::new(),
+		} * width * 2.0;
+
+		while next <= length {
+			let base = from + direction * next + outward * width;
+			shapes.push(Shape::LineSegment {
+				points: [base, base + tick],
+				stroke: PathStroke::new(width / 2.0, color),
+			});
+			next += spacing;
+		}
+		next -= length;
+	}
+
+	shapes
+}
+
+// lines parallel to the way, left and right in its direction
+fn side_lines(points: &[Pos2], width: f32, left: Color32, right: Color32) -> [Shape; 2] {
+	[
+		Shape::line(offset_polyline(points, width), PathStroke::new(width, left)),
+		Shape::line(offset_polyline(points, -width), PathStroke::new(width, right)),
+	]
+}
+
+fn sidewalk_color(value: TagValue) -> Color32 {
+	match value {
+		TagValue::Yes => SIDEWALK_YES_COLOR,
+		TagValue::No => SIDEWALK_NO_COLOR,
+		TagValue::Separate => SIDEWALK_SEPARATE_COLOR,
+		_ => SIDEWALK_UNKNOWN_COLOR,
+	}
+}
+
+fn cycleway_color(value: TagValue) -> Color32 {
+	match value {
+		TagValue::Lane => CYCLEWAY_LANE_COLOR,
+		TagValue::Track => CYCLEWAY_TRACK_COLOR,
+		TagValue::SharedLane => CYCLEWAY_SHARED_LANE_COLOR,
+		TagValue::ShareBusway => CYCLEWAY_SHARE_BUSWAY_COLOR,
+		TagValue::Separate => CYCLEWAY_SEPARATE_COLOR,
+		TagValue::No => CYCLEWAY_NO_COLOR,
+		_ => CYCLEWAY_UNKNOWN_COLOR,
+	}
+}
+
+// highways without a surface stand out, the smoothness is a thinner line in the middle
+pub fn surfaces(way: &Way, points: &[Pos2], color: Color32, width: f32) -> Vec<Shape> {
+	if !surface_relevant(&way.tags) {
+		return default(points, color, width);
+	}
+
+	let mut shapes = Vec::with_capacity(2);
+	let surface = way.tags.get("surface").map(String::as_str);
+
+	if surface.is_none() {
+		shapes.push(Shape::line(points.to_vec(), PathStroke::new(width + SURFACE_MISSING_HALO, SURFACE_MISSING_COLOR.gamma_multiply(0.4))));
+	}
+	shapes.push(Shape::line(points.to_vec(), PathStroke::new(width, surface_color(surface))));
+
+	if let Some(smoothness) = way.tags.get("smoothness") {
+		shapes.push(Shape::line(points.to_vec(), PathStroke::new(width / 3.0, smoothness_color(smoothness))));
+	}
+
+	shapes
+}
+
+// similar surfaces share a color, paved ones are gray
+fn surface_color(surface: Option<&str>) -> Color32 {
+	match surface {
+		None => SURFACE_MISSING_COLOR,
+		Some("asphalt" | "paved" | "chipseal") => SURFACE_PAVED_COLOR,
+		Some("concrete" | "concrete:plates" | "concrete:lanes") => SURFACE_CONCRETE_COLOR,
+		Some("paving_stones" | "bricks") => SURFACE_PAVING_STONES_COLOR,
+		Some("sett" | "cobblestone" | "unhewn_cobblestone") => SURFACE_SETT_COLOR,
+		Some("compacted" | "fine_gravel" | "unpaved") => SURFACE_COMPACTED_COLOR,
+		Some("gravel" | "pebblestone" | "rock") => SURFACE_GRAVEL_COLOR,
+		Some("ground" | "dirt" | "earth" | "mud") => SURFACE_GROUND_COLOR,
+		Some("grass" | "grass_paver") => SURFACE_GRASS_COLOR,
+		Some("sand") => SURFACE_SAND_COLOR,
+		Some("wood" | "woodchips") => SURFACE_WOOD_COLOR,
+		Some(_) => SURFACE_OTHER_COLOR,
+	}
+}
+
+fn smoothness_color(smoothness: &str) -> Color32 {
+	match smoothness {
+		"excellent" | "good" => SMOOTHNESS_GOOD_COLOR,
+		"intermediate" => SMOOTHNESS_INTERMEDIATE_COLOR,
+		"bad" | "very_bad" => SMOOTHNESS_BAD_COLOR,
+		"horrible" | "very_horrible" => SMOOTHNESS_HORRIBLE_COLOR,
+		"impassable" => SMOOTHNESS_IMPASSABLE_COLOR,
+		_ => SURFACE_OTHER_COLOR,
+	}
+}
+
+fn parking_color(value: TagValue) -> Color32 {
+	match value {
+		TagValue::Lane => PARKING_LANE_COLOR,
+		TagValue::StreetSide => PARKING_STREET_SIDE_COLOR,
+		TagValue::OnKerb => PARKING_ON_KERB_COLOR,
+		TagValue::HalfOnKerb => PARKING_HALF_ON_KERB_COLOR,
+		TagValue::Shoulder => PARKING_SHOULDER_COLOR,
+		TagValue::Separate => PARKING_SEPARATE_COLOR,
+		TagValue::No => PARKING_NO_COLOR,
+		_ => PARKING_UNKNOWN_COLOR,
+	}
+}
+
+// unrestricted parking has no stripe
+fn restriction_color(restriction: &str) -> Option<Color32> {
+	match restriction {
+		"free" => None,
+		"no_stopping" => Some(PARKING_NO_STOPPING_COLOR),
+		"no_parking" | "no_standing" => Some(PARKING_NO_PARKING_COLOR),
+		"loading_only" | "charging_only" | "disabled" => Some(PARKING_RESTRICTED_COLOR),
+		_ => Some(PARKING_RESTRICTION_UNKNOWN_COLOR),
+	}
+}
+
 pub fn sidewalks_relevant(tags: &osm_parser::Tags) -> bool {
 	if let Some(highway) = tags.get("highway") {
 		HIGHWAYS_WITH_SIDEWALK.contains(&highway.as_str())
 	} else { false }
 }
 
+pub fn parking_relevant(tags: &osm_parser::Tags) -> bool {
+	if let Some(highway) = tags.get("highway") {
+		HIGHWAYS_WITH_PARKING.contains(&highway.as_str())
+	} else { false }
+}
 
-pub fn sidewalks_ui(ui: &mut egui::Ui, pos: Pos2) -> bool {
+// roads where bicycles are allowed and cycle infrastructure is mapped on the road itself
+pub fn cycleways_relevant(tags: &osm_parser::Tags) -> bool {
+	if let Some(highway) = tags.get("highway") {
+		HIGHWAYS_WITH_CYCLEWAY.contains(&highway.as_str())
+	} else { false }
+}
+
+pub fn surface_relevant(tags: &osm_parser::Tags) -> bool {
+	tags.get("highway").is_some_and(|h| h != PROPOSED && h != CONSTRUCTION)
+}
+
+pub fn sidewalks_ui(ui: &mut egui::Ui, pos: Pos2, tags: &mut Tags) -> bool {
 	let mut open = true;
 
 	Window::new("Sidewalks")
 		.default_pos(pos)
 		.open(&mut open)
+		.resizable(false)
+		.show(ui.ctx(), |ui| {
+			let mut surface = SubAttribute2D::new(tags, &SIDEWALK, "surface");
+			let mut surface_changed = false;
+
+			side_key_grid(ui, tags, &SIDEWALK, sidewalk_color);
+
+			Grid::new("sidewalks_surface").show(ui, |ui| {
+				surface_changed |= sub_value_row(ui, "Left surface", &mut surface.left);
+				surface_changed |= sub_value_row(ui, "Right surface", &mut surface.right);
+			});
+
+			if surface_changed {
+				surface.apply(tags, &SIDEWALK, "surface");
+			}
+
+			ui.separator();
+			normalize_button(ui, tags, &SIDEWALK);
+		});
+
+	open
+}
+
+pub fn cycleways_ui(ui: &mut egui::Ui, pos: Pos2, tags: &mut Tags) -> bool {
+	let mut open = true;
+
+	Window::new("Cycleways")
+		.default_pos(pos)
+		.open(&mut open)
+		.resizable(false)
 		.show(ui.ctx(), |ui| {
-			// TODO: UI
-			ui.label("TODO");
+			side_key_grid(ui, tags, &CYCLEWAY, cycleway_color);
+
+			ui.separator();
+			normalize_button(ui, tags, &CYCLEWAY);
 		});
 
 	open
 }
+
+// left and right value selection, written back in canonical form
+fn side_key_grid(ui: &mut egui::Ui, tags: &mut Tags, key: &SideKey, color: fn(TagValue) -> Color32) {
+	let mut attr = Attribute2D::new(tags, key);
+	let mut changed = false;
+
+	Grid::new(key.key).show(ui, |ui| {
+		changed |= tag_value_row(ui, "Left", &mut attr.left, key, color);
+		changed |= tag_value_row(ui, "Right", &mut attr.right, key, color);
+	});
+
+	if changed {
+		attr.apply(tags, key);
+	}
+}
+
+// offers rewriting mixed tagging that the value selection would not touch
+fn normalize_button(ui: &mut egui::Ui, tags: &mut Tags, key: &SideKey) {
+	let mut normalized = tags.clone();
+	let normalizable = Attribute2D::normalize(&mut normalized, key) && normalized != *tags;
+
+	if ui.add_enabled(normalizable, Button::new("Normalize"))
+		.on_hover_text(format!("Rewrite {} tags into canonical form", key.key))
+		.clicked() {
+		*tags = normalized;
+	}
+}
+
+pub fn parking_ui(ui: &mut egui::Ui, pos: Pos2, tags: &mut Tags) -> bool {
+	let mut open = true;
+
+	Window::new("Parking")
+		.default_pos(pos)
+		.open(&mut open)
+		.resizable(false)
+		.show(ui.ctx(), |ui| {
+			side_key_grid(ui, tags, &PARKING, parking_color);
+
+			for (sub, choices) in [("orientation", PARKING_ORIENTATIONS.as_slice()), ("restriction", PARKING_RESTRICTIONS.as_slice())] {
+				let mut attr = SubAttribute2D::new(tags, &PARKING, sub);
+				let mut changed = false;
+
+				ui.separator();
+				Grid::new(format!("parking_{sub}")).show(ui, |ui| {
+					changed |= sub_choice_row(ui, &format!("Left {sub}"), &mut attr.left, choices);
+					changed |= sub_choice_row(ui, &format!("Right {sub}"), &mut attr.right, choices);
+				});
+
+				if changed {
+					attr.apply(tags, &PARKING, sub);
+				}
+			}
+
+			ui.separator();
+			normalize_button(ui, tags, &PARKING);
+		});
+
+	open
+}
+
+pub fn surface_ui(ui: &mut egui::Ui, pos: Pos2, tags: &mut Tags) -> bool {
+	let mut open = true;
+
+	Window::new("Surface")
+		.default_pos(pos)
+		.open(&mut open)
+		.resizable(false)
+		.show(ui.ctx(), |ui| {
+			for (key, choices) in [("surface", SURFACES.as_slice()), ("smoothness", SMOOTHNESSES.as_slice())] {
+				let mut value = tags.get(key).cloned();
+				let text = value.clone().unwrap_or("unknown".to_string());
+				let mut changed = false;
+
+				ComboBox::from_label(key).selected_text(text).show_ui(ui, |ui| {
+					for choice in choices {
+						changed |= ui.selectable_value(&mut value, Some(choice.to_string()), *choice).changed();
+					}
+					changed |= ui.selectable_value(&mut value, None, "unknown").changed();
+				});
+
+				if changed {
+					match value {
+						Some(v) => tags.insert(key.to_string(), v),
+						None => tags.remove(key),
+					};
+				}
+			}
+		});
+
+	open
+}
+
+// colors and the number of highways with each surface and smoothness, the most common first
+// ways per surface and smoothness in the whole dataset, counted again only when the data is edited
+#[derive(Default)]
+pub struct SurfaceCounts {
+	revision: Option<u64>,
+	surfaces: Vec<(Option<String>, usize)>,
+	smoothnesses: Vec<(Option<String>, usize)>,
+}
+
+impl SurfaceCounts {
+	fn update(&mut self, data: &OsmData, revision: u64) {
+		if self.revision == Some(revision) { return; }
+
+		let highways: Vec<&Way> = data.ways.values().filter(|w| surface_relevant(&w.tags) && !is_area(w)).collect();
+		let owned = |counts: Vec<(Option<&str>, usize)>| counts.into_iter().map(|(v, c)| (v.map(String::from), c)).collect();
+		self.surfaces = owned(count_values(&highways, "surface"));
+		self.smoothnesses = owned(count_values(&highways, "smoothness"));
+		self.revision = Some(revision);
+	}
+}
+
+pub fn surface_legend(ui: &mut egui::Ui, counts: &mut SurfaceCounts, data: &OsmData, revision: u64) {
+	counts.update(data, revision);
+
+	Window::new("Surfaces in the loaded data")
+		.resizable(false)
+		// below the attribution
+		.anchor(Align2::LEFT_TOP, [10., 60.])
+		.show(ui.ctx(), |ui| {
+			Grid::new("surface_legend").show(ui, |ui| {
+				for (value, count) in &counts.surfaces {
+					let value = value.as_deref();
+					let text = RichText::new(value.unwrap_or("missing")).color(surface_color(value));
+					ui.label(text);
+					ui.label(count.to_string());
+					ui.end_row();
+				}
+			});
+
+			ui.separator();
+			Grid::new("smoothness_legend").show(ui, |ui| {
+				for (value, count) in &counts.smoothnesses {
+					let value = value.as_deref();
+					let color = value.map_or(DEFAULT_COLOR, smoothness_color);
+					ui.label(RichText::new(value.unwrap_or("unknown")).color(color));
+					ui.label(count.to_string());
+					ui.end_row();
+				}
+			});
+		});
+}
+
+// ways per value of a key, None counts the ways without it
+fn count_values<'a>(ways: &[&'a Way], key: &str) -> Vec<(Option<&'a str>, usize)> {
+	let mut counts: HashMap<Option<&str>, usize> = HashMap::new();
+	for way in ways {
+		*counts.entry(way.tags.get(key).map(String::as_str)).or_default() += 1;
+	}
+
+	let mut counts: Vec<_> = counts.into_iter().collect();
+	counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+	counts
+}
+
+fn tag_value_row(ui: &mut egui::Ui, label: &str, value: &mut TagValue, key: &SideKey, color: fn(TagValue) -> Color32) -> bool {
+	let mut changed = false;
+
+	ui.label(label);
+	for v in key.choices() {
+		let text = RichText::new(v.label()).color(color(v));
+		changed |= ui.selectable_value(value, v, text).changed();
+	}
+	ui.end_row();
+
+	changed
+}
+
+// free-form value of a nested key, an empty field removes the tag
+fn sub_value_row(ui: &mut egui::Ui, label: &str, value: &mut Option<String>) -> bool {
+	let mut text = value.clone().unwrap_or_default();
+
+	ui.label(label);
+	let changed = ui.text_edit_singleline(&mut text).changed();
+	ui.end_row();
+
+	if changed {
+		*value = Some(text).filter(|t| !t.is_empty());
+	}
+
+	changed
+}
+
+// value of a nested key out of a fixed vocabulary, unknown removes the tag
+fn sub_choice_row(ui: &mut egui::Ui, label: &str, value: &mut Option<String>, choices: &[&str]) -> bool {
+	let mut changed = false;
+
+	ui.label(label);
+	for choice in choices {
+		if ui.selectable_label(value.as_deref() == Some(choice), *choice).clicked() {
+			*value = Some(choice.to_string());
+			changed = true;
+		}
+	}
+	changed |= ui.selectable_value(value, None, "unknown").changed();
+	ui.end_row();
+
+	changed
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::test_util::tags;
+
+	fn assert_close(actual: &[Pos2], expected: &[Pos2]) {
+		assert_eq!(actual.len(), expected.len());
+		for (a, e) in actual.iter().zip(expected) {
+			assert!(a.distance(*e) < 1e-4, "{actual:?} != {expected:?}");
+		}
+	}
+
+	#[test]
+	fn offset_corner() {
+		// screen coordinates, y points down, so left of a way going right is up
+		let points = [Pos2::new(0.0, 0.0), Pos2::new(10.0, 0.0), Pos2::new(10.0, 10.0)];
+
+		assert_close(&offset_polyline(&points, 1.0), &[Pos2::new(0.0, -1.0), Pos2::new(11.0, -1.0), Pos2::new(11.0, 10.0)]);
+		assert_close(&offset_polyline(&points, -1.0), &[Pos2::new(0.0, 1.0), Pos2::new(9.0, 1.0), Pos2::new(9.0, 10.0)]);
+	}
+
+	#[test]
+	fn offset_closed_and_degenerate() {
+		let square = [Pos2::new(0.0, 0.0), Pos2::new(10.0, 0.0), Pos2::new(10.0, 10.0), Pos2::new(0.0, 10.0), Pos2::new(0.0, 0.0)];
+		let offset = offset_polyline(&square, -1.0);
+		// the joint where the ring closes is mitered as well
+		assert_eq!(offset.first(), offset.last());
+		assert_close(&offset[..1], &[Pos2::new(1.0, 1.0)]);
+
+		// repeated points and sharp turns stay finite
+		let spike = [Pos2::new(0.0, 0.0), Pos2::new(10.0, 0.0), Pos2::new(10.0, 0.0), Pos2::new(0.0, 0.1)];
+		assert!(offset_polyline(&spike, 1.0).iter().all(|p| p.x.is_finite() && p.distance(Pos2::new(5.0, 0.0)) < 10.0));
+	}
+
+	#[test]
+	fn free_parking_is_not_restricted() {
+		assert_eq!(restriction_color("free"), None);
+		assert_eq!(restriction_color("disabled"), Some(PARKING_RESTRICTED_COLOR));
+		assert_eq!(restriction_color("residents"), Some(PARKING_RESTRICTION_UNKNOWN_COLOR));
+		assert!(PARKING_RESTRICTIONS.contains(&"free"));
+	}
+
+	#[test]
+	fn surfaces_counted_by_value() {
+		let way = |id: i64, pairs: &[(&str, &str)]| Way {
+			id,
+			nodes: vec![1, 2],
+			tags: tags(pairs),
+		};
+		let ways = [
+			way(1, &[("highway", "residential"), ("surface", "asphalt")]),
+			way(2, &[("highway", "track"), ("surface", "gravel")]),
+			way(3, &[("highway", "service"), ("surface", "asphalt")]),
+			way(4, &[("highway", "footway")]),
+		];
+		let refs: Vec<&Way> = ways.iter().collect();
+
+		assert_eq!(count_values(&refs, "surface"), [(Some("asphalt"), 2), (None, 1), (Some("gravel"), 1)]);
+		assert_eq!(surface_color(None), SURFACE_MISSING_COLOR);
+		assert_eq!(surface_color(Some("paving_stones")), SURFACE_PAVING_STONES_COLOR);
+		assert!(!surface_relevant(&Tags::new()));
+
+		// counted once per revision
+		let mut data = OsmData::default();
+		data.ways.insert(1, ways[0].clone());
+		let mut counts = SurfaceCounts::default();
+		counts.update(&data, 1);
+		data.ways.clear();
+		counts.update(&data, 1);
+		assert_eq!(counts.surfaces, [(Some("asphalt".to_string()), 1)]);
+		counts.update(&data, 2);
+		assert!(counts.surfaces.is_empty());
+	}
+
+	#[test]
+	fn oneway_arrows_along_way() {
+		assert_eq!(oneway(&tags(&[("oneway", "yes")])), Some(1.0));
+		assert_eq!(oneway(&tags(&[("oneway", "-1")])), Some(-1.0));
+		assert_eq!(oneway(&tags(&[("oneway", "no"), ("junction", "roundabout")])), None);
+		assert_eq!(oneway(&tags(&[("junction", "roundabout")])), Some(1.0));
+
+		// the spacing carries over the joint
+		let spacing = ONEWAY_ARROW_SPACING;
+		let points = [Pos2::ZERO, Pos2::new(spacing, 0.0), Pos2::new(spacing, spacing * 2.0)];
+		let tips: Vec<Pos2> = oneway_arrows(&points, -1.0, 4.0).iter()
+			.map(|s| match s {
+				Shape::Path(path) => path.points[0],
+				_ => panic!("arrows are polygons"),
+			})
+			.collect();
+
+		let size = (4.0 * ONEWAY_ARROW_SIZE).max(ONEWAY_ARROW_MIN_SIZE);
+		assert_close(&tips, &[
+			Pos2::new(spacing / 2.0 - size, 0.0),
+			Pos2::new(spacing, spacing / 2.0 - size),
+			Pos2::new(spacing, spacing * 1.5 - size),
+		]);
+	}
+}
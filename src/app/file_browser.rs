@@ -0,0 +1,172 @@
+use super::windows::MenuAction;
+use eframe::egui::{Button, Color32, Context, ScrollArea, Window};
+use std::path::PathBuf;
+
+// file dialog drawn with egui, so no native dialog dependency is needed
+#[derive(Default)]
+pub struct FileBrowser {
+	// the action the chosen file is for, None while the browser is closed
+	action: Option<MenuAction>,
+	dir: PathBuf,
+	file_name: String,
+	entries: Vec<Entry>,
+	error: Option<String>,
+	// file that was chosen but replaces an existing file or discards unsaved changes, waiting for confirmation
+	pending: Option<PathBuf>,
+	// only read the part of a PBF extract that is currently visible
+	pub clip_to_view: bool,
+}
+
+struct Entry {
+	name: String,
+	is_dir: bool,
+}
+
+impl FileBrowser {
+	pub fn open(&mut self, action: MenuAction, file_name: &str) {
+		if self.dir.as_os_str().is_empty() {
+			self.dir = std::env::current_dir().unwrap_or_default();
+		}
+
+		self.action = Some(action);
+		self.file_name = file_name.to_string();
+		self.pending = None;
+		self.refresh();
+	}
+
+	// returns the chosen path together with the action it was opened for,
+	// `unsaved_changes` are the edits that opening another file throws away
+	pub fn show(&mut self, ctx: &Context, unsaved_changes: usize) -> Option<(MenuAction, PathBuf)> {
+		let action = self.action?;
+		let mut open = true;
+		let mut navigate = None;
+		let mut chosen = None;
+		let mut cancel = false;
+
+		let (title, confirm) = match action {
+			MenuAction::Open => ("Open", "Open"),
+			MenuAction::OpenStyle => ("Load style", "Load"),
+			MenuAction::ExportChanges => ("Export changes", "Export"),
+			_ => ("Save as", "Save"),
+		};
+
+		Window::new(title)
+			.collapsible(false)
+			.open(&mut open)
+			.default_size([400., 300.])
+			.show(ctx, |ui| {
+				ui.horizontal(|ui| {
+					if ui.button("⬆").on_hover_text("Parent directory").clicked() {
+						navigate = self.dir.parent().map(PathBuf::from);
+					}
+					ui.label(self.dir.display().to_string());
+				});
+				ui.separator();
+
+				ScrollArea::vertical().max_height(250.).auto_shrink([false, true]).show(ui, |ui| {
+					for entry in &self.entries {
+						if entry.is_dir {
+							if ui.selectable_label(false, format!("🗀 {}", entry.name)).clicked() {
+								navigate = Some(self.dir.join(&entry.name));
+							}
+						} else {
+							let response = ui.selectable_label(self.file_name == entry.name, format!("🗋 {}", entry.name));
+							if response.clicked() {
+								self.file_name = entry.name.clone();
+							}
+							if response.double_clicked() {
+								chosen = Some(self.dir.join(&entry.name));
+							}
+						}
+					}
+				});
+				ui.separator();
+
+				ui.horizontal(|ui| {
+					ui.text_edit_singleline(&mut self.file_name);
+					if ui.add_enabled(!self.file_name.is_empty(), Button::new(confirm)).clicked() {
+						chosen = Some(self.dir.join(&self.file_name));
+					}
+				});
+
+				if action == MenuAction::Open {
+					ui.checkbox(&mut self.clip_to_view, "Only load the visible area of PBF files");
+				}
+
+				if let Some(path) = &self.pending {
+					let (warning, proceed) = match action {
+						MenuAction::Open => (format!("Opening {} discards {unsaved_changes} unsaved changes and the undo history.", path.display()), "Discard changes"),
+						_ => (format!("{} already exists.", path.display()), "Replace"),
+					};
+					ui.separator();
+					ui.colored_label(Color32::YELLOW, warning);
+					ui.horizontal(|ui| {
+						if ui.button(proceed).clicked() {
+							chosen = Some(path.clone());
+						}
+						if ui.button("Cancel").clicked() {
+							cancel = true;
+						}
+					});
+				}
+
+				if let Some(error) = &self.error {
+					ui.colored_label(Color32::RED, error);
+				}
+			});
+
+		if let Some(dir) = navigate {
+			self.dir = dir;
+			self.refresh();
+		}
+
+		// files are only replaced and unsaved changes only discarded once that is confirmed
+		let needs_confirmation = |path: &PathBuf| match action {
+			MenuAction::Open => unsaved_changes > 0,
+			MenuAction::OpenStyle => false,
+			_ => path.exists(),
+		};
+		if let Some(path) = chosen.take() {
+			if needs_confirmation(&path) && self.pending.as_ref() != Some(&path) {
+				self.pending = Some(path);
+			} else {
+				chosen = Some(path);
+			}
+		}
+		// another file was picked in the meantime
+		if cancel || self.pending.as_ref().is_some_and(|p| *p != self.dir.join(&self.file_name)) {
+			self.pending = None;
+		}
+
+		if !open || chosen.is_some() {
+			self.action = None;
+		}
+
+		chosen.map(|path| (action, path))
+	}
+
+	// directories first, hidden entries are skipped
+	fn refresh(&mut self) {
+		self.entries.clear();
+		self.error = None;
+
+		let read_dir = match std::fs::read_dir(&self.dir) {
+			Ok(read_dir) => read_dir,
+			Err(e) => {
+				self.error = Some(format!("Could not read directory: {e}"));
+				return;
+			},
+		};
+
+		self.entries = read_dir
+			.filter_map(|e| e.ok())
+			.map(|e| Entry {
+				name: e.file_name().to_string_lossy().into_owned(),
+				is_dir: e.path().is_dir(),
+			})
+			.filter(|e| !e.name.starts_with('.'))
+			.collect();
+
+		self.entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+	}
+}
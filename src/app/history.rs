@@ -0,0 +1,151 @@
+use super::document::{Document, ElementRef, Snapshot};
+use osm_parser::{Id, Tags};
+use std::time::{Duration, Instant};
+
+const MERGE_INTERVAL: Duration = Duration::from_secs(1);
+
+// an edit to the document, executed through History so it can be undone
+#[derive(Debug, Clone)]
+pub enum Command {
+	SetWayTags(Id, Tags),
+	DeleteWay(Id),
+	DeleteNode(Id),
+	DeleteRelation(Id),
+}
+
+#[derive(Default)]
+pub struct History {
+	entries: Vec<Entry>,
+	// number of entries currently applied, entries after it can be redone
+	position: usize,
+}
+
+struct Entry {
+	description: String,
+	before: Vec<Snapshot>,
+	after: Vec<Snapshot>,
+	// consecutive edits of the same tags on the same object are merged, e.g. typing a value
+	merge_key: Option<(ElementRef, Vec<String>)>,
+	time: Instant,
+}
+
+impl Command {
+	pub fn description(&self, document: &Document) -> String {
+		match self {
+			Command::SetWayTags(id, tags) => {
+				let old = document.data().ways.get(id).map(|w| &w.tags);
+				describe_tags(old, tags, &format!("way {id}"))
+			},
+			Command::DeleteWay(id) => format!("Delete way {id}"),
+			Command::DeleteNode(id) => format!("Delete node {id}"),
+			Command::DeleteRelation(id) => format!("Delete relation {id}"),
+		}
+	}
+
+	fn merge_key(&self, document: &Document) -> Option<(ElementRef, Vec<String>)> {
+		let (element, old, new) = match self {
+			Command::SetWayTags(id, tags) => (ElementRef::Way(*id), &document.data().ways.get(id)?.tags, tags),
+			_ => return None,
+		};
+
+		Some((element, changed_keys(old, new)))
+	}
+
+	fn execute(self, document: &mut Document) {
+		match self {
+			Command::SetWayTags(id, tags) => document.set_way_tags(id, tags),
+			Command::DeleteWay(id) => document.delete_way(id),
+			Command::DeleteNode(id) => document.delete_node(id),
+			Command::DeleteRelation(id) => document.delete_relation(id),
+		}
+	}
+}
+
+impl History {
+	pub fn execute(&mut self, document: &mut Document, command: Command) {
+		let description = command.description(document);
+		let merge_key = command.merge_key(document);
+		let (before, after) = document.record(|document| command.execute(document));
+
+		// nothing changed
+		if before.is_empty() { return; }
+
+		self.entries.truncate(self.position);
+
+		if let Some(last) = self.entries.last_mut() {
+			if merge_key.is_some() && last.merge_key == merge_key && last.time.elapsed() < MERGE_INTERVAL {
+				last.description = description;
+				last.after = after;
+				last.time = Instant::now();
+				return;
+			}
+		}
+
+		self.entries.push(Entry { description, before, after, merge_key, time: Instant::now() });
+		self.position = self.entries.len();
+	}
+
+	pub fn undo(&mut self, document: &mut Document) {
+		if self.position > 0 {
+			self.jump(document, self.position - 1);
+		}
+	}
+
+	pub fn redo(&mut self, document: &mut Document) {
+		if self.position < self.entries.len() {
+			self.jump(document, self.position + 1);
+		}
+	}
+
+	// undoes or redoes entries until `position` entries are applied
+	pub fn jump(&mut self, document: &mut Document, position: usize) {
+		let position = position.min(self.entries.len());
+
+		while self.position > position {
+			self.position -= 1;
+			document.restore(&self.entries[self.position].before);
+		}
+		while self.position < position {
+			document.restore(&self.entries[self.position].after);
+			self.position += 1;
+		}
+
+		// a restored entry must not absorb the next edit
+		if let Some(last) = self.position.checked_sub(1).and_then(|i| self.entries.get_mut(i)) {
+			last.merge_key = None;
+		}
+	}
+
+	pub fn position(&self) -> usize {
+		self.position
+	}
+
+	pub fn descriptions(&self) -> impl Iterator<Item = &str> {
+		self.entries.iter().map(|e| e.description.as_str())
+	}
+}
+
+fn changed_keys(old: &Tags, new: &Tags) -> Vec<String> {
+	let mut keys: Vec<String> = old.keys().chain(new.keys())
+		.filter(|k| old.get(*k) != new.get(*k))
+		.cloned()
+		.collect();
+
+	keys.sort();
+	keys.dedup();
+	keys
+}
+
+// "Set sidewalk:left=separate on way 12345"
+fn describe_tags(old: Option<&Tags>, new: &Tags, target: &str) -> String {
+	let Some(old) = old else { return format!("Change tags of {target}") };
+	let keys = changed_keys(old, new);
+
+	match keys.as_slice() {
+		[key] => match new.get(key) {
+			Some(value) => format!("Set {key}={value} on {target}"),
+			None => format!("Remove {key} from {target}"),
+		},
+		keys => format!("Change {} tags on {target}", keys.len()),
+	}
+}
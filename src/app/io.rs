@@ -0,0 +1,127 @@
+pub mod json;
+pub mod osc;
+pub mod pbf;
+pub mod xml;
+
+use super::document::{Bounds, Document};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Format {
+	Json,
+	Xml,
+	Pbf,
+}
+
+#[derive(Debug)]
+pub enum Error {
+	Io(std::io::Error),
+	Json(serde_json::Error),
+	Xml(quick_xml::Error),
+	Pbf(osmpbf::Error),
+	Parse(String),
+	UnknownFormat,
+}
+
+impl Display for Error {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Error::Io(e) => write!(f, "could not read file: {e}"),
+			Error::Json(e) => write!(f, "invalid json: {e}"),
+			Error::Xml(e) => write!(f, "invalid xml: {e}"),
+			Error::Pbf(e) => write!(f, "invalid pbf: {e}"),
+			Error::Parse(e) => write!(f, "could not parse osm data: {e}"),
+			Error::UnknownFormat => write!(f, "not an OSM JSON, XML or PBF file"),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+	fn from(value: std::io::Error) -> Self { Error::Io(value) }
+}
+
+impl From<serde_json::Error> for Error {
+	fn from(value: serde_json::Error) -> Self { Error::Json(value) }
+}
+
+impl From<quick_xml::Error> for Error {
+	fn from(value: quick_xml::Error) -> Self { Error::Xml(value) }
+}
+
+impl From<quick_xml::events::attributes::AttrError> for Error {
+	fn from(value: quick_xml::events::attributes::AttrError) -> Self { Error::Xml(value.into()) }
+}
+
+impl From<osmpbf::Error> for Error {
+	fn from(value: osmpbf::Error) -> Self { Error::Pbf(value) }
+}
+
+// reads any supported file, `clip` limits PBF extracts to an area since they are often too large to edit whole
+pub fn load(path: impl AsRef<Path>, clip: Option<Bounds>) -> Result<Document, Error> {
+	let path = path.as_ref();
+
+	match detect(path)? {
+		Format::Json => json::load(path),
+		Format::Xml => xml::load(path),
+		Format::Pbf => pbf::load(path, clip),
+	}
+}
+
+// the content decides, e.g. overpass saves JSON with an .osm extension, the extension is only a fallback
+pub fn detect(path: &Path) -> Result<Format, Error> {
+	use std::io::Read;
+
+	let mut head = Vec::new();
+	std::fs::File::open(path)?.take(64).read_to_end(&mut head)?;
+
+	if let Some(format) = detect_content(&head) {
+		return Ok(format);
+	}
+
+	let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+	if name.ends_with(".pbf") {
+		Ok(Format::Pbf)
+	} else if name.ends_with(".osm") || name.ends_with(".xml") {
+		Ok(Format::Xml)
+	} else if name.ends_with(".json") || name.ends_with(".geojson") {
+		Ok(Format::Json)
+	} else {
+		Err(Error::UnknownFormat)
+	}
+}
+
+fn detect_content(head: &[u8]) -> Option<Format> {
+	// pbf files start with the length of the first blob header, which names its type
+	if head.windows(9).any(|w| w == b"OSMHeader") {
+		return Some(Format::Pbf);
+	}
+
+	let text = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head);
+	match text.iter().find(|b| !b.is_ascii_whitespace()) {
+		Some(b'{') => Some(Format::Json),
+		Some(b'<') => Some(Format::Xml),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn detect_by_content() {
+		assert_eq!(detect_content(b"  {\"version\": 0.6"), Some(Format::Json));
+		assert_eq!(detect_content(b"\xEF\xBB\xBF<?xml version=\"1.0\"?>"), Some(Format::Xml));
+		assert_eq!(detect_content(b"\0\0\0\x0e\n\tOSMHeader\x18"), Some(Format::Pbf));
+		assert_eq!(detect_content(b"name,lat,lon"), None);
+	}
+
+	#[test]
+	fn detect_school() {
+		// JSON despite the .osm extension
+		assert_eq!(detect(Path::new("school.osm")).unwrap(), Format::Json);
+	}
+}
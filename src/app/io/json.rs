@@ -0,0 +1,150 @@
+use super::super::document::{Bounds, Document, ElementRef, Member, Meta, Relation};
+use super::Error;
+use osm_parser::{Coordinate, Node, OsmData, Tags, Way};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+pub fn load(path: &Path) -> Result<Document, Error> {
+	read(&std::fs::read_to_string(path)?)
+}
+
+pub fn save(document: &Document, path: impl AsRef<Path>) -> io::Result<()> {
+	let mut out = BufWriter::new(File::create(path)?);
+	write(document, &mut out)?;
+	out.flush()
+}
+
+// current dataset in the same shape as the OSM JSON it was loaded from, tags are sorted for stable output
+// and deleted objects are left out since the format has no way of marking them
+pub fn write(document: &Document, out: impl Write) -> io::Result<()> {
+	let mut nodes: Vec<_> = document.data().nodes.values().collect();
+	let mut ways: Vec<_> = document.data().ways.values().collect();
+	let mut relations: Vec<_> = document.relations().values().collect();
+	nodes.sort_by_key(|n| n.id);
+	ways.sort_by_key(|w| w.id);
+	relations.sort_by_key(|r| r.id);
+
+	let mut elements = Vec::with_capacity(nodes.len() + ways.len() + relations.len());
+
+	for node in nodes {
+		let mut e = Map::new();
+		e.insert("type".into(), "node".into());
+		e.insert("id".into(), node.id.into());
+		e.insert("lat".into(), node.pos.lat.into());
+		e.insert("lon".into(), node.pos.lon.into());
+		insert_meta(&mut e, document.meta(ElementRef::Node(node.id)));
+		if !node.tags.is_empty() {
+			e.insert("tags".into(), json!(node.tags.iter().collect::<BTreeMap<_, _>>()));
+		}
+		elements.push(Value::Object(e));
+	}
+
+	for way in ways {
+		let mut e = Map::new();
+		e.insert("type".into(), "way".into());
+		e.insert("id".into(), way.id.into());
+		insert_meta(&mut e, document.meta(ElementRef::Way(way.id)));
+		e.insert("nodes".into(), json!(way.nodes));
+		if !way.tags.is_empty() {
+			e.insert("tags".into(), json!(way.tags.iter().collect::<BTreeMap<_, _>>()));
+		}
+		elements.push(Value::Object(e));
+	}
+
+	for relation in relations {
+		let mut e = Map::new();
+		e.insert("type".into(), "relation".into());
+		e.insert("id".into(), relation.id.into());
+		insert_meta(&mut e, document.meta(ElementRef::Relation(relation.id)));
+		e.insert("members".into(), relation.members.iter().map(|m| json!({
+			"type": m.element.type_name(),
+			"ref": m.element.id(),
+			"role": m.role,
+		})).collect());
+		if !relation.tags.is_empty() {
+			e.insert("tags".into(), json!(relation.tags.iter().collect::<BTreeMap<_, _>>()));
+		}
+		elements.push(Value::Object(e));
+	}
+
+	let mut root = Map::new();
+	root.insert("version".into(), "0.6".into());
+	root.insert("generator".into(), "walkers-editor".into());
+	if let Some(b) = document.bounds() {
+		root.insert("bounds".into(), json!({
+			"minlat": b.min_lat,
+			"minlon": b.min_lon,
+			"maxlat": b.max_lat,
+			"maxlon": b.max_lon,
+		}));
+	}
+	root.insert("elements".into(), Value::Array(elements));
+
+	serde_json::to_writer(out, &root)?;
+	Ok(())
+}
+
+fn insert_meta(e: &mut Map<String, Value>, meta: Option<&Meta>) {
+	let Some(meta) = meta else { return };
+
+	e.insert("timestamp".into(), meta.timestamp.clone().into());
+	e.insert("version".into(), meta.version.into());
+	e.insert("changeset".into(), meta.changeset.into());
+	e.insert("user".into(), meta.user.clone().into());
+	e.insert("uid".into(), meta.uid.into());
+}
+
+// the file is parsed once, objects and their metadata come from the same elements
+fn read(json: &str) -> Result<Document, Error> {
+	let value: serde_json::Value = serde_json::from_str(json)?;
+	let mut data = OsmData::default();
+	let mut relations = HashMap::new();
+	let mut meta = HashMap::new();
+
+	let b = &value["bounds"];
+	let bounds = match (b["minlat"].as_f64(), b["minlon"].as_f64(), b["maxlat"].as_f64(), b["maxlon"].as_f64()) {
+		(Some(min_lat), Some(min_lon), Some(max_lat), Some(max_lon)) => Some(Bounds { min_lat, min_lon, max_lat, max_lon }),
+		_ => None,
+	};
+
+	for e in value["elements"].as_array().into_iter().flatten() {
+		let Some(id) = e["id"].as_i64() else { continue };
+		let Some(element) = e["type"].as_str().and_then(|t| ElementRef::from_type(t, id)) else { continue };
+		let tags: Tags = e["tags"].as_object().into_iter().flatten()
+			.filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+			.collect();
+
+		match element {
+			ElementRef::Node(id) => {
+				let (Some(lat), Some(lon)) = (e["lat"].as_f64(), e["lon"].as_f64()) else { continue };
+				data.nodes.insert(id, Node { id, pos: Coordinate { lat, lon }, tags });
+			},
+			ElementRef::Way(id) => {
+				let nodes = e["nodes"].as_array().into_iter().flatten().filter_map(|n| n.as_i64()).collect();
+				data.ways.insert(id, Way { id, nodes, tags });
+			},
+			ElementRef::Relation(id) => {
+				let members = e["members"].as_array().into_iter().flatten()
+					.filter_map(|m| Some(Member {
+						element: ElementRef::from_type(m["type"].as_str()?, m["ref"].as_i64()?)?,
+						role: m["role"].as_str().unwrap_or_default().to_string(),
+					}))
+					.collect();
+				relations.insert(id, Relation { id, members, tags });
+			},
+		}
+
+		meta.insert(element, Meta {
+			version: e["version"].as_u64().unwrap_or_default() as u32,
+			changeset: e["changeset"].as_u64().unwrap_or_default(),
+			user: e["user"].as_str().unwrap_or_default().to_string(),
+			uid: e["uid"].as_u64().unwrap_or_default(),
+			timestamp: e["timestamp"].as_str().unwrap_or_default().to_string(),
+		});
+	}
+
+	Ok(Document::new(data, relations, meta, bounds))
+}
@@ -0,0 +1,167 @@
+use super::super::document::{Change, Document, ElementRef};
+use super::xml::{write_node, write_relation, write_way};
+use quick_xml::events::{BytesDecl, Event};
+use quick_xml::Writer;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+pub fn save(document: &Document, path: impl AsRef<Path>) -> io::Result<()> {
+	let mut out = BufWriter::new(File::create(path)?);
+	write(document, &mut out)?;
+	out.flush()
+}
+
+// local changes as an osmChange document
+pub fn write(document: &Document, out: impl Write) -> io::Result<()> {
+	let mut writer = Writer::new_with_indent(out, b' ', 2);
+	let changes = document.changes();
+
+	writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+	writer.create_element("osmChange")
+		.with_attributes([("version", "0.6"), ("generator", "walkers-editor")])
+		.write_inner_content(|writer| {
+			for (block, change) in [("create", Change::Created), ("modify", Change::Modified), ("delete", Change::Deleted)] {
+				// nodes sort before ways and ways before relations, deletions have to remove objects before the ones they reference
+				let mut elements: Vec<_> = changes.iter().filter(|(_, c)| *c == change).map(|(e, _)| *e).collect();
+				if change == Change::Deleted { elements.reverse(); }
+				if elements.is_empty() { continue; }
+
+				writer.create_element(block).write_inner_content(|writer| {
+					for element in elements {
+						write_element(writer, document, element, change)?;
+					}
+					Ok(())
+				})?;
+			}
+			Ok(())
+		})?;
+
+	Ok(())
+}
+
+fn write_element<W: Write>(writer: &mut Writer<W>, document: &Document, element: ElementRef, change: Change) -> io::Result<()> {
+	let data = match change {
+		Change::Deleted => document.deleted(),
+		_ => document.data(),
+	};
+
+	let mut attributes = vec![("id", element.id().to_string())];
+	// new objects are created with a placeholder id and have no version yet,
+	// the API rejects changes to existing objects without one
+	if change != Change::Created {
+		let Some(meta) = document.meta(element).filter(|m| m.version > 0) else {
+			let message = format!("{} {} has no version, the data must be loaded from a file with versions", element.type_name(), element.id());
+			return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+		};
+		attributes.push(("version", meta.version.to_string()));
+	}
+
+	match element {
+		ElementRef::Node(id) => write_node(writer, &data.nodes[&id], &attributes),
+		ElementRef::Way(id) => write_way(writer, &data.ways[&id], &attributes),
+		ElementRef::Relation(id) => {
+			let relations = if change == Change::Deleted { document.deleted_relations() } else { document.relations() };
+			write_relation(writer, &relations[&id], &attributes)
+		},
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use osm_parser::{Coordinate, Tags};
+	use quick_xml::Reader;
+
+	fn attribute(e: &quick_xml::events::BytesStart, name: &str) -> Option<String> {
+		e.try_get_attribute(name).unwrap().map(|a| a.unescape_value().unwrap().into_owned())
+	}
+
+	#[test]
+	fn export_school() {
+		let mut document = super::super::load("school.osm", None).unwrap();
+
+		// the lowest id that the edit changes, so that the test does not depend on the order of the map
+		let modified = document.data().ways.values()
+			.filter(|w| w.tags.contains_key("highway") && w.tags.get("sidewalk").is_none_or(|s| s != "both"))
+			.map(|w| w.id)
+			.min()
+			.unwrap();
+		let mut tags = document.data().ways[&modified].tags.clone();
+		tags.insert("sidewalk".to_string(), "both".to_string());
+		document.set_way_tags(modified, tags);
+
+		let deleted = *document.data().ways.keys().filter(|id| **id != modified).min().unwrap();
+		document.delete_way(deleted);
+
+		let a = document.create_node(Coordinate { lat: 50.06, lon: 10.216 }, Tags::new());
+		let b = document.create_node(Coordinate { lat: 50.061, lon: 10.217 }, Tags::new());
+		let created = document.create_way(vec![a, b], Tags::from([("highway".to_string(), "footway".to_string())]));
+
+		let mut out = Vec::new();
+		write(&document, &mut out).unwrap();
+		let xml = String::from_utf8(out).unwrap();
+
+		let mut reader = Reader::from_str(&xml);
+		let mut path: Vec<String> = Vec::new();
+		let mut blocks = Vec::new();
+		let mut found = Vec::new();
+
+		loop {
+			let (e, empty) = match reader.read_event().unwrap() {
+				Event::Start(e) => (e, false),
+				Event::Empty(e) => (e, true),
+				Event::End(_) => { path.pop(); continue; },
+				Event::Eof => break,
+				_ => continue,
+			};
+			let name = String::from_utf8(e.name().as_ref().to_vec()).unwrap();
+
+			match path.len() {
+				0 => assert_eq!(name, "osmChange"),
+				1 => blocks.push(name.clone()),
+				2 => {
+					let id: i64 = attribute(&e, "id").unwrap().parse().unwrap();
+					let version = attribute(&e, "version");
+					let block = path[1].as_str();
+
+					match block {
+						"create" => assert!(id < 0 && version.is_none()),
+						"modify" | "delete" => assert!(id > 0 && version.is_some()),
+						_ => panic!("unexpected block {block}"),
+					}
+					found.push((block.to_string(), name.clone(), id));
+				},
+				_ => assert!(name == "tag" || name == "nd" || name == "member"),
+			}
+
+			if !empty { path.push(name); }
+		}
+
+		assert!(path.is_empty());
+		assert_eq!(blocks, ["create", "modify", "delete"]);
+		assert!(found.contains(&("create".to_string(), "node".to_string(), a)));
+		assert!(found.contains(&("create".to_string(), "way".to_string(), created)));
+		assert!(found.contains(&("modify".to_string(), "way".to_string(), modified)));
+		assert!(found.contains(&("delete".to_string(), "way".to_string(), deleted)));
+
+		// ways are created after and deleted before their nodes
+		let position = |block: &str, kind: &str| found.iter().position(|(b, k, _)| b == block && k == kind);
+		assert!(position("create", "node") < position("create", "way"));
+		if let Some(node) = position("delete", "node") {
+			assert!(position("delete", "way") < Some(node));
+		}
+	}
+
+	#[test]
+	fn changes_without_version() {
+		let mut data = osm_parser::OsmData::default();
+		data.ways.insert(1, osm_parser::Way { id: 1, nodes: Vec::new(), tags: Tags::new() });
+		let mut document = Document::new(data, Default::default(), Default::default(), None);
+		document.set_way_tags(1, Tags::from([("highway".to_string(), "path".to_string())]));
+
+		let error = write(&document, Vec::new()).unwrap_err();
+		assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+	}
+}
@@ -0,0 +1,147 @@
+use super::super::document::{Bounds, Document, ElementRef, Member, Meta, Relation};
+use super::Error;
+use osm_parser::{Coordinate, Id, Node, OsmData, Tags, Way};
+use osmpbf::{Element, ElementReader, Info, RelMemberType};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// reads a PBF extract, with `clip` only ways with a node inside it are kept, together with all of their nodes,
+// and relations with a member that was kept
+pub fn load(path: &Path, clip: Option<Bounds>) -> Result<Document, Error> {
+	let mut data = OsmData::default();
+	let mut relations = HashMap::new();
+	let mut meta = HashMap::new();
+	// nodes outside `clip` that kept ways refer to
+	let mut needed: HashSet<Id> = HashSet::new();
+
+	// pbf files store nodes, then ways, then relations, so whether a member is inside is known when an object is read
+	ElementReader::from_path(path)?.for_each(|element| {
+		if let Some((node, m)) = read_node(&element) {
+			if clip.is_none_or(|b| b.contains(&node.pos)) {
+				insert_node(&mut data, &mut meta, node, m);
+			}
+		} else if let Element::Way(w) = element {
+			let way = Way { id: w.id(), nodes: w.refs().collect(), tags: read_tags(w.tags()) };
+			if clip.is_some() && !way.nodes.iter().any(|id| data.nodes.contains_key(id)) {
+				return;
+			}
+
+			needed.extend(way.nodes.iter().filter(|id| !data.nodes.contains_key(id)));
+			if let Some(m) = read_meta(&w.info()) {
+				meta.insert(ElementRef::Way(way.id), m);
+			}
+			data.ways.insert(way.id, way);
+		} else if let Element::Relation(r) = element {
+			let members: Vec<Member> = r.members()
+				.map(|m| Member {
+					element: match m.member_type {
+						RelMemberType::Node => ElementRef::Node(m.member_id),
+						RelMemberType::Way => ElementRef::Way(m.member_id),
+						RelMemberType::Relation => ElementRef::Relation(m.member_id),
+					},
+					role: m.role().unwrap_or_default().to_string(),
+				})
+				.collect();
+
+			let kept = |m: &Member| match m.element {
+				ElementRef::Node(id) => data.nodes.contains_key(&id) || needed.contains(&id),
+				ElementRef::Way(id) => data.ways.contains_key(&id),
+				ElementRef::Relation(id) => relations.contains_key(&id),
+			};
+			if clip.is_some() && !members.iter().any(kept) {
+				return;
+			}
+
+			if let Some(m) = read_meta(&r.info()) {
+				meta.insert(ElementRef::Relation(r.id()), m);
+			}
+			relations.insert(r.id(), Relation { id: r.id(), members, tags: read_tags(r.tags()) });
+		}
+	})?;
+
+	// second pass for the nodes of ways crossing the border of `clip`
+	if !needed.is_empty() {
+		ElementReader::from_path(path)?.for_each(|element| {
+			if let Some((node, m)) = read_node(&element) {
+				if needed.contains(&node.id) {
+					insert_node(&mut data, &mut meta, node, m);
+				}
+			}
+		})?;
+	}
+
+	Ok(Document::new(data, relations, meta, clip))
+}
+
+fn insert_node(data: &mut OsmData, meta: &mut HashMap<ElementRef, Meta>, node: Node, m: Option<Meta>) {
+	if let Some(m) = m {
+		meta.insert(ElementRef::Node(node.id), m);
+	}
+	data.nodes.insert(node.id, node);
+}
+
+// plain and dense nodes
+fn read_node(element: &Element) -> Option<(Node, Option<Meta>)> {
+	match element {
+		Element::Node(n) => Some((
+			Node { id: n.id(), pos: Coordinate { lat: n.lat(), lon: n.lon() }, tags: read_tags(n.tags()) },
+			read_meta(&n.info()),
+		)),
+		Element::DenseNode(n) => Some((
+			Node { id: n.id(), pos: Coordinate { lat: n.lat(), lon: n.lon() }, tags: read_tags(n.tags()) },
+			n.info().map(|i| Meta {
+				version: i.version() as u32,
+				changeset: i.changeset() as u64,
+				user: i.user().unwrap_or_default().to_string(),
+				uid: i.uid() as u64,
+				timestamp: timestamp(i.milli_timestamp()),
+			}),
+		)),
+		_ => None,
+	}
+}
+
+fn read_tags<'a>(tags: impl Iterator<Item = (&'a str, &'a str)>) -> Tags {
+	tags.map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+// extracts without metadata have no version
+fn read_meta(info: &Info) -> Option<Meta> {
+	Some(Meta {
+		version: info.version()? as u32,
+		changeset: info.changeset().unwrap_or_default() as u64,
+		user: info.user().and_then(|u| u.ok()).unwrap_or_default().to_string(),
+		uid: info.uid().unwrap_or_default() as u64,
+		timestamp: info.milli_timestamp().map(timestamp).unwrap_or_default(),
+	})
+}
+
+// "2023-11-14T22:13:20Z" like the API, the date conversion is from http://howardhinnant.github.io/date_algorithms.html
+fn timestamp(millis: i64) -> String {
+	let seconds = millis.div_euclid(1000);
+	let (days, time) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+
+	let z = days + 719_468;
+	let era = z.div_euclid(146_097);
+	let doe = z.rem_euclid(146_097);
+	let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + i64::from(month <= 2);
+
+	format!("{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z", time / 3600, time % 3600 / 60, time % 60)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn timestamps() {
+		assert_eq!(timestamp(0), "1970-01-01T00:00:00Z");
+		assert_eq!(timestamp(1_700_000_000_000), "2023-11-14T22:13:20Z");
+		assert_eq!(timestamp(951_782_400_000), "2000-02-29T00:00:00Z");
+	}
+}
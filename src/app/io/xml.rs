@@ -0,0 +1,410 @@
+use super::super::document::{Bounds, Change, Document, ElementRef, Member, Meta, Relation};
+use super::Error;
+use osm_parser::{Coordinate, Id, Node, OsmData, Tags, Way};
+use quick_xml::events::{BytesDecl, BytesStart, Event};
+use quick_xml::{Reader, Writer};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+// object whose child elements are being read
+enum Current {
+	Node(Node),
+	Way(Way),
+	Relation(Relation),
+	// no longer exists, children are ignored
+	Skipped,
+}
+
+pub fn load(path: &Path) -> Result<Document, Error> {
+	read(&std::fs::read_to_string(path)?)
+}
+
+// OSM XML as served by the API and written by JOSM and osmium, the changes JOSM marks with an action attribute
+// and new objects with negative ids are loaded as local changes
+pub fn read(text: &str) -> Result<Document, Error> {
+	let mut reader = Reader::from_str(text);
+	let mut data = OsmData::default();
+	let mut deleted = OsmData::default();
+	let mut relations = HashMap::new();
+	let mut deleted_relations = HashMap::new();
+	let mut meta = HashMap::new();
+	let mut changes = HashMap::new();
+	let mut bounds = None;
+	let mut current = None;
+	// whether the current object goes into `deleted`
+	let mut is_deleted = false;
+
+	loop {
+		let (e, empty) = match reader.read_event()? {
+			Event::Start(e) => (e, false),
+			Event::Empty(e) => (e, true),
+			Event::End(e) => {
+				if matches!(e.name().as_ref(), b"node" | b"way" | b"relation") {
+					if is_deleted {
+						finish(&mut deleted, &mut deleted_relations, current.take());
+					} else {
+						finish(&mut data, &mut relations, current.take());
+					}
+				}
+				continue;
+			},
+			Event::Eof => break,
+			_ => continue,
+		};
+
+		match e.name().as_ref() {
+			b"bounds" => bounds = Some(Bounds {
+				min_lat: required(&e, "minlat")?,
+				min_lon: required(&e, "minlon")?,
+				max_lat: required(&e, "maxlat")?,
+				max_lon: required(&e, "maxlon")?,
+			}),
+			name @ (b"node" | b"way" | b"relation") => {
+				let id = required(&e, "id")?;
+				let element = ElementRef::from_type(&String::from_utf8_lossy(name), id).unwrap();
+				let change = match attribute(&e, "action")?.as_deref() {
+					// created and deleted again before it was uploaded
+					Some("delete") if id < 0 => None,
+					Some("delete") => Some(Change::Deleted),
+					_ if id < 0 => Some(Change::Created),
+					Some("modify") => Some(Change::Modified),
+					_ => None,
+				};
+				// invisible objects are deleted on the server
+				let skipped = (id < 0 && change.is_none())
+					|| attribute(&e, "visible")?.as_deref() == Some("false");
+				is_deleted = change == Some(Change::Deleted);
+
+				current = Some(match element {
+					_ if skipped => Current::Skipped,
+					ElementRef::Node(_) => Current::Node(Node {
+						id,
+						pos: Coordinate { lat: required(&e, "lat")?, lon: required(&e, "lon")? },
+						tags: Tags::new(),
+					}),
+					ElementRef::Way(_) => Current::Way(Way { id, nodes: Vec::new(), tags: Tags::new() }),
+					ElementRef::Relation(_) => Current::Relation(Relation { id, members: Vec::new(), tags: Tags::new() }),
+				});
+
+				if !skipped {
+					if let Some(m) = read_meta(&e)? {
+						meta.insert(element, m);
+					}
+					if let Some(change) = change {
+						changes.insert(element, change);
+					}
+				}
+
+				if empty {
+					if is_deleted {
+						finish(&mut deleted, &mut deleted_relations, current.take());
+					} else {
+						finish(&mut data, &mut relations, current.take());
+					}
+				}
+			},
+			b"nd" => if let Some(Current::Way(way)) = &mut current {
+				way.nodes.push(required(&e, "ref")?);
+			},
+			b"member" => if let Some(Current::Relation(relation)) = &mut current {
+				let kind: String = required(&e, "type")?;
+				let element = ElementRef::from_type(&kind, required(&e, "ref")?)
+					.ok_or_else(|| Error::Parse(format!("invalid member type \"{kind}\"")))?;
+				relation.members.push(Member { element, role: attribute(&e, "role")?.unwrap_or_default() });
+			},
+			b"tag" => {
+				let tags = match &mut current {
+					Some(Current::Node(node)) => &mut node.tags,
+					Some(Current::Way(way)) => &mut way.tags,
+					Some(Current::Relation(relation)) => &mut relation.tags,
+					_ => continue,
+				};
+				tags.insert(required(&e, "k")?, required(&e, "v")?);
+			},
+			_ => {},
+		}
+	}
+
+	Ok(Document::new(data, relations, meta, bounds).with_changes(deleted, deleted_relations, changes))
+}
+
+fn finish(data: &mut OsmData, relations: &mut HashMap<Id, Relation>, current: Option<Current>) {
+	match current {
+		Some(Current::Node(node)) => { data.nodes.insert(node.id, node); },
+		Some(Current::Way(way)) => { data.ways.insert(way.id, way); },
+		Some(Current::Relation(relation)) => { relations.insert(relation.id, relation); },
+		_ => {},
+	}
+}
+
+// objects created in JOSM have no version and therefore no metadata
+fn read_meta(e: &BytesStart) -> Result<Option<Meta>, Error> {
+	let Some(version) = optional(e, "version")? else { return Ok(None) };
+
+	Ok(Some(Meta {
+		version,
+		changeset: optional(e, "changeset")?.unwrap_or_default(),
+		user: attribute(e, "user")?.unwrap_or_default(),
+		uid: optional(e, "uid")?.unwrap_or_default(),
+		timestamp: attribute(e, "timestamp")?.unwrap_or_default(),
+	}))
+}
+
+fn attribute(e: &BytesStart, name: &str) -> Result<Option<String>, Error> {
+	match e.try_get_attribute(name)? {
+		Some(a) => Ok(Some(a.unescape_value()?.into_owned())),
+		None => Ok(None),
+	}
+}
+
+fn optional<T: FromStr>(e: &BytesStart, name: &str) -> Result<Option<T>, Error> {
+	attribute(e, name)?
+		.map(|v| v.parse().map_err(|_| Error::Parse(format!("invalid {name} \"{v}\""))))
+		.transpose()
+}
+
+fn required<T: FromStr>(e: &BytesStart, name: &str) -> Result<T, Error> {
+	let element = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+	optional(e, name)?.ok_or_else(|| Error::Parse(format!("<{element}> without {name}")))
+}
+
+pub fn save(document: &Document, path: impl AsRef<Path>) -> io::Result<()> {
+	let mut out = BufWriter::new(File::create(path)?);
+	write(document, &mut out)?;
+	out.flush()
+}
+
+// full dataset in the format JOSM saves, local changes are marked with an action attribute
+pub fn write(document: &Document, out: impl Write) -> io::Result<()> {
+	let mut writer = Writer::new_with_indent(out, b' ', 2);
+
+	let mut nodes: Vec<_> = document.data().nodes.values().chain(document.deleted().nodes.values()).collect();
+	let mut ways: Vec<_> = document.data().ways.values().chain(document.deleted().ways.values()).collect();
+	let mut relations: Vec<_> = document.relations().values().chain(document.deleted_relations().values()).collect();
+	nodes.sort_by_key(|n| n.id);
+	ways.sort_by_key(|w| w.id);
+	relations.sort_by_key(|r| r.id);
+
+	writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+	writer.create_element("osm")
+		.with_attributes([("version", "0.6"), ("generator", "walkers-editor")])
+		.write_inner_content(|writer| {
+			if let Some(b) = document.bounds() {
+				let bounds = [b.min_lat, b.min_lon, b.max_lat, b.max_lon].map(|v| format!("{v:.7}"));
+				writer.create_element("bounds")
+					.with_attributes([
+						("minlat", bounds[0].as_str()), ("minlon", bounds[1].as_str()),
+						("maxlat", bounds[2].as_str()), ("maxlon", bounds[3].as_str()),
+					])
+					.write_empty()?;
+			}
+
+			for node in nodes {
+				write_node(writer, node, &attributes(document, ElementRef::Node(node.id)))?;
+			}
+			for way in ways {
+				write_way(writer, way, &attributes(document, ElementRef::Way(way.id)))?;
+			}
+			for relation in relations {
+				write_relation(writer, relation, &attributes(document, ElementRef::Relation(relation.id)))?;
+			}
+			Ok(())
+		})?;
+
+	Ok(())
+}
+
+// id, change marker and the metadata the object was downloaded with
+fn attributes(document: &Document, element: ElementRef) -> Vec<(&'static str, String)> {
+	let mut attributes = vec![("id", element.id().to_string())];
+
+	match document.change(element) {
+		Some(Change::Deleted) => attributes.push(("action", "delete".to_string())),
+		Some(_) => attributes.push(("action", "modify".to_string())),
+		None => {},
+	}
+
+	if let Some(meta) = document.meta(element) {
+		attributes.extend([
+			("timestamp", meta.timestamp.clone()),
+			("uid", meta.uid.to_string()),
+			("user", meta.user.clone()),
+			("version", meta.version.to_string()),
+			("changeset", meta.changeset.to_string()),
+		]);
+	}
+
+	attributes
+}
+
+pub(super) fn write_node<W: Write>(writer: &mut Writer<W>, node: &Node, attributes: &[(&str, String)]) -> io::Result<()> {
+	let lat = format!("{:.7}", node.pos.lat);
+	let lon = format!("{:.7}", node.pos.lon);
+	let element = writer.create_element("node")
+		.with_attributes(attributes.iter().map(|(k, v)| (*k, v.as_str())))
+		.with_attributes([("lat", lat.as_str()), ("lon", lon.as_str())]);
+
+	if node.tags.is_empty() {
+		element.write_empty()?;
+	} else {
+		element.write_inner_content(|writer| write_tags(writer, &node.tags))?;
+	}
+
+	Ok(())
+}
+
+pub(super) fn write_way<W: Write>(writer: &mut Writer<W>, way: &Way, attributes: &[(&str, String)]) -> io::Result<()> {
+	writer.create_element("way")
+		.with_attributes(attributes.iter().map(|(k, v)| (*k, v.as_str())))
+		.write_inner_content(|writer| {
+			for node in &way.nodes {
+				writer.create_element("nd")
+					.with_attribute(("ref", node.to_string().as_str()))
+					.write_empty()?;
+			}
+
+			write_tags(writer, &way.tags)
+		})?;
+
+	Ok(())
+}
+
+pub(super) fn write_relation<W: Write>(writer: &mut Writer<W>, relation: &Relation, attributes: &[(&str, String)]) -> io::Result<()> {
+	writer.create_element("relation")
+		.with_attributes(attributes.iter().map(|(k, v)| (*k, v.as_str())))
+		.write_inner_content(|writer| {
+			for member in &relation.members {
+				writer.create_element("member")
+					.with_attributes([
+						("type", member.element.type_name()),
+						("ref", member.element.id().to_string().as_str()),
+						("role", member.role.as_str()),
+					])
+					.write_empty()?;
+			}
+
+			write_tags(writer, &relation.tags)
+		})?;
+
+	Ok(())
+}
+
+// sorted so that output is stable
+fn write_tags<W: Write>(writer: &mut Writer<W>, tags: &Tags) -> io::Result<()> {
+	let mut tags: Vec<_> = tags.iter().collect();
+	tags.sort();
+
+	for (k, v) in tags {
+		writer.create_element("tag")
+			.with_attributes([("k", k.as_str()), ("v", v.as_str())])
+			.write_empty()?;
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn round_trip_school() {
+		let mut document = super::super::load("school.osm", None).unwrap();
+		let way = *document.data().ways.keys().min().unwrap();
+		document.delete_way(way);
+
+		let mut out = Vec::new();
+		write(&document, &mut out).unwrap();
+		let read = read(std::str::from_utf8(&out).unwrap()).unwrap();
+		let data = read.data();
+
+		assert_eq!(read.bounds(), document.bounds());
+		assert_eq!(data.nodes.len(), document.data().nodes.len());
+		assert_eq!(data.ways.len(), document.data().ways.len());
+		assert!(!data.ways.contains_key(&way));
+		for (id, node) in &data.nodes {
+			let original = &document.data().nodes[id];
+			assert!((node.pos.lat - original.pos.lat).abs() < 1e-7);
+			assert_eq!(node.tags, original.tags);
+			assert_eq!(read.meta(ElementRef::Node(*id)), document.meta(ElementRef::Node(*id)));
+		}
+		for (id, way) in &data.ways {
+			assert_eq!(way.nodes, document.data().ways[id].nodes);
+			assert_eq!(way.tags, document.data().ways[id].tags);
+		}
+	}
+	#[test]
+	fn pending_changes_round_trip() {
+		let mut document = super::super::load("school.osm", None).unwrap();
+		let modified = *document.data().ways.keys().min().unwrap();
+		let mut tags = document.data().ways[&modified].tags.clone();
+		tags.insert("note".to_string(), "round trip".to_string());
+		document.set_way_tags(modified, tags);
+		document.delete_way(*document.data().ways.keys().max().unwrap());
+
+		let a = document.create_node(Coordinate { lat: 50.06, lon: 10.216 }, Tags::new());
+		let b = document.create_node(Coordinate { lat: 50.061, lon: 10.217 }, Tags::new());
+		document.create_way(vec![a, b], Tags::from([("highway".to_string(), "footway".to_string())]));
+
+		let mut saved = Vec::new();
+		write(&document, &mut saved).unwrap();
+		let mut read = read(std::str::from_utf8(&saved).unwrap()).unwrap();
+
+		let osc = |document: &Document| {
+			let mut out = Vec::new();
+			super::super::osc::write(document, &mut out).unwrap();
+			String::from_utf8(out).unwrap()
+		};
+		assert_eq!(read.change_count(), document.change_count());
+		assert_eq!(osc(&read), osc(&document));
+
+		// new objects continue below the ids in the file
+		let c = read.create_node(Coordinate { lat: 50.06, lon: 10.216 }, Tags::new());
+		assert!(c < a.min(b));
+	}
+
+	#[test]
+	fn relation_members() {
+		let mut document = read(r#"<?xml version="1.0" encoding="UTF-8"?>
+			<osm version="0.6">
+				<node id="1" lat="50.0" lon="10.0"/>
+				<node id="2" lat="50.1" lon="10.0"/>
+				<node id="3" lat="50.1" lon="10.1"/>
+				<way id="10"><nd ref="1"/><nd ref="2"/><nd ref="3"/><nd ref="1"/></way>
+				<relation id="100" version="3">
+					<member type="way" ref="10" role="outer"/>
+					<member type="node" ref="3" role=""/>
+					<tag k="type" v="multipolygon"/>
+				</relation>
+			</osm>"#).unwrap();
+
+		let relation = &document.relations()[&100];
+		assert_eq!(relation.members[0], Member { element: ElementRef::Way(10), role: "outer".to_string() });
+		assert_eq!(relation.members[1].element, ElementRef::Node(3));
+		assert_eq!(relation.tags["type"], "multipolygon");
+		assert_eq!(document.meta(ElementRef::Relation(100)).unwrap().version, 3);
+
+		// the relation loses the deleted way and is uploaded as modified, the member node is kept
+		document.delete_way(10);
+		assert_eq!(document.relations()[&100].members.len(), 1);
+		assert!(document.contains(ElementRef::Node(3)) && !document.contains(ElementRef::Node(1)));
+		assert_eq!(document.change(ElementRef::Relation(100)), Some(Change::Modified));
+
+		// without members it is deleted in the same edit, and kept as deleted when saved
+		let (before, _) = document.record(|document| document.delete_node(3));
+		assert!(!document.contains(ElementRef::Relation(100)));
+		let mut saved = Vec::new();
+		write(&document, &mut saved).unwrap();
+		let read = read(std::str::from_utf8(&saved).unwrap()).unwrap();
+		assert_eq!(read.change(ElementRef::Relation(100)), Some(Change::Deleted));
+		assert_eq!(read.deleted_relations()[&100].tags["type"], "multipolygon");
+
+		document.restore(&before);
+		assert_eq!(document.relations()[&100].members.len(), 1);
+		assert_eq!(document.change(ElementRef::Relation(100)), Some(Change::Modified));
+		assert!(document.deleted_relations().is_empty());
+	}
+}
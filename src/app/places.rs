@@ -1,5 +0,0 @@
-use walkers::Position;
-
-pub fn school() -> Position {
-    Position::from_lon_lat(10.216837, 50.059561)
-}
@@ -0,0 +1,164 @@
+use osm_parser::{Id, OsmData};
+use rstar::{RTree, RTreeObject, AABB};
+use std::collections::HashMap;
+
+// lon, lat
+pub type Point = [f64; 2];
+
+// what an entry of the index stands for, segments are numbered by their first node in the way
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Item {
+	Node(Id),
+	Way(Id),
+	Segment(Id, usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+	item: Item,
+	envelope: AABB<Point>,
+}
+
+impl RTreeObject for Entry {
+	type Envelope = AABB<Point>;
+
+	fn envelope(&self) -> Self::Envelope {
+		self.envelope
+	}
+}
+
+// r-tree over nodes, way segments and the extent of every way, ways are found by their extent so that
+// areas around the queried region are found as well
+#[derive(Default)]
+pub struct SpatialIndex {
+	tree: RTree<Entry>,
+	// entries as they were inserted, needed to remove them again after an object changed
+	nodes: HashMap<Id, Entry>,
+	ways: HashMap<Id, Vec<Entry>>,
+}
+
+impl SpatialIndex {
+	pub fn new(data: &OsmData) -> Self {
+		let nodes: HashMap<Id, Entry> = data.nodes.keys().filter_map(|id| Some((*id, node_entry(*id, data)?))).collect();
+		let ways: HashMap<Id, Vec<Entry>> = data.ways.keys().map(|id| (*id, way_entries(*id, data))).collect();
+		let entries = nodes.values().chain(ways.values().flatten()).cloned().collect();
+
+		Self { tree: RTree::bulk_load(entries), nodes, ways }
+	}
+
+	// also updates the ways the node is part of, before and after it moved
+	pub fn update_node(&mut self, id: Id, data: &OsmData) {
+		let old = self.nodes.remove(&id);
+		if let Some(entry) = &old {
+			self.tree.remove(entry);
+		}
+
+		if let Some(entry) = node_entry(id, data) {
+			self.tree.insert(entry.clone());
+			self.nodes.insert(id, entry);
+		}
+
+		let mut ways: Vec<Id> = old.iter().chain(self.nodes.get(&id))
+			.flat_map(|e| self.tree.locate_in_envelope_intersecting(&e.envelope))
+			.filter_map(|e| match e.item {
+				Item::Segment(way, _) => Some(way),
+				_ => None,
+			})
+			.filter(|way| data.ways.get(way).is_some_and(|w| w.nodes.contains(&id)))
+			.collect();
+		ways.sort();
+		ways.dedup();
+
+		for way in ways {
+			self.update_way(way, data);
+		}
+	}
+
+	pub fn update_way(&mut self, id: Id, data: &OsmData) {
+		for entry in self.ways.remove(&id).into_iter().flatten() {
+			self.tree.remove(&entry);
+		}
+
+		if data.ways.contains_key(&id) {
+			let entries = way_entries(id, data);
+			for entry in &entries {
+				self.tree.insert(entry.clone());
+			}
+			self.ways.insert(id, entries);
+		}
+	}
+
+	// everything whose extent intersects the rectangle between two corners
+	pub fn locate(&self, a: Point, b: Point) -> impl Iterator<Item = Item> + '_ {
+		self.tree.locate_in_envelope_intersecting(&AABB::from_corners(a, b)).map(|e| e.item)
+	}
+
+	#[cfg(test)]
+	pub fn len(&self) -> usize {
+		self.tree.size()
+	}
+}
+
+fn point(id: &Id, data: &OsmData) -> Option<Point> {
+	data.nodes.get(id).map(|n| [n.pos.lon, n.pos.lat])
+}
+
+fn node_entry(id: Id, data: &OsmData) -> Option<Entry> {
+	Some(Entry { item: Item::Node(id), envelope: AABB::from_point(point(&id, data)?) })
+}
+
+// segments with a node that is not loaded are left out
+fn way_entries(id: Id, data: &OsmData) -> Vec<Entry> {
+	let way = &data.ways[&id];
+	let points: Vec<Option<Point>> = way.nodes.iter().map(|n| point(n, data)).collect();
+
+	let mut entries: Vec<Entry> = points.windows(2).enumerate()
+		.filter_map(|(i, s)| Some(Entry { item: Item::Segment(id, i), envelope: AABB::from_corners(s[0]?, s[1]?) }))
+		.collect();
+
+	let loaded: Vec<Point> = points.into_iter().flatten().collect();
+	if !loaded.is_empty() {
+		entries.push(Entry { item: Item::Way(id), envelope: AABB::from_points(&loaded) });
+	}
+
+	entries
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use osm_parser::{Coordinate, Node, Tags};
+
+	fn node(data: &mut OsmData, id: Id, lon: f64, lat: f64) {
+		data.nodes.insert(id, Node { id, pos: Coordinate { lat, lon }, tags: Tags::new() });
+	}
+
+	#[test]
+	fn update_moved_node() {
+		let mut data = OsmData::default();
+		node(&mut data, 1, 0.0, 0.0);
+		node(&mut data, 2, 1.0, 0.0);
+		node(&mut data, 3, 2.0, 0.0);
+		data.ways.insert(10, osm_parser::Way { id: 10, nodes: vec![1, 2, 3], tags: Tags::new() });
+		let mut index = SpatialIndex::new(&data);
+		assert_eq!(index.len(), 3 + 2 + 1);
+
+		let found = |index: &SpatialIndex, a: Point, b: Point| {
+			let mut items: Vec<Item> = index.locate(a, b).collect();
+			items.sort_by_key(|i| format!("{i:?}"));
+			items
+		};
+		assert_eq!(found(&index, [0.4, -0.1], [0.6, 0.1]), [Item::Segment(10, 0), Item::Way(10)]);
+
+		// the segments around the node follow it
+		node(&mut data, 2, 1.0, 5.0);
+		index.update_node(2, &data);
+		assert_eq!(found(&index, [0.4, -0.1], [0.6, 0.1]), [Item::Segment(10, 0), Item::Way(10)]);
+		assert_eq!(found(&index, [0.9, 4.9], [1.1, 5.1]), [Item::Node(2), Item::Segment(10, 0), Item::Segment(10, 1), Item::Way(10)]);
+		assert!(!found(&index, [0.9, -0.1], [1.1, 0.1]).contains(&Item::Node(2)));
+
+		data.ways.remove(&10);
+		index.update_way(10, &data);
+		assert_eq!(index.len(), 3);
+	}
+}
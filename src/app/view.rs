@@ -0,0 +1,58 @@
+use super::document::Bounds;
+use super::editor::coordinate_to_pos;
+use eframe::egui::Vec2;
+use std::f64::consts::PI;
+use walkers::{MapMemory, Position};
+
+const TILE_SIZE: f64 = 256.0;
+const MAX_ZOOM: f64 = 19.0;
+// share of the viewport kept free around fitted bounds
+const MARGIN: f64 = 0.1;
+
+// centers the map on `bounds` and zooms so that they fill a viewport of `size`
+pub fn fit(map_memory: &mut MapMemory, bounds: Bounds, size: Vec2) {
+	map_memory.center_at(coordinate_to_pos(&bounds.center()));
+
+	let width = (mercator_x(bounds.max_lon) - mercator_x(bounds.min_lon)) * TILE_SIZE;
+	let height = (mercator_y(bounds.min_lat) - mercator_y(bounds.max_lat)) * TILE_SIZE;
+
+	let zoom_x = (size.x as f64 * (1.0 - MARGIN) / width).log2();
+	let zoom_y = (size.y as f64 * (1.0 - MARGIN) / height).log2();
+	let zoom = zoom_x.min(zoom_y);
+
+	// a single point has no extent
+	let zoom = if zoom.is_finite() { zoom.clamp(0.0, MAX_ZOOM) } else { MAX_ZOOM };
+	let _ = map_memory.set_zoom(zoom);
+}
+
+// area shown in a viewport of `size` around `center`
+pub fn visible_bounds(center: Position, zoom: f64, size: Vec2) -> Bounds {
+	let world = TILE_SIZE * 2f64.powf(zoom);
+	let (x, y) = (mercator_x(center.lon()), mercator_y(center.lat()));
+	let (half_width, half_height) = (size.x as f64 / 2.0 / world, size.y as f64 / 2.0 / world);
+
+	Bounds {
+		min_lat: inverse_mercator_y(y + half_height),
+		min_lon: inverse_mercator_x(x - half_width),
+		max_lat: inverse_mercator_y(y - half_height),
+		max_lon: inverse_mercator_x(x + half_width),
+	}
+}
+
+// web mercator, 0..1 across the world
+fn mercator_x(lon: f64) -> f64 {
+	(lon + 180.0) / 360.0
+}
+
+fn mercator_y(lat: f64) -> f64 {
+	let lat = lat.to_radians();
+	(1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0
+}
+
+fn inverse_mercator_x(x: f64) -> f64 {
+	x * 360.0 - 180.0
+}
+
+fn inverse_mercator_y(y: f64) -> f64 {
+	(PI * (1.0 - 2.0 * y)).sinh().atan().to_degrees()
+}
@@ -1,9 +1,90 @@
+use super::document::{Change, ElementRef, Member, Meta};
+use super::editor::detail::LevelOfDetail;
 use super::editor::visual::Visualization;
+use super::history::History;
 use super::providers::Provider;
 use eframe::egui;
-use egui::{Align2, Grid, RichText, Ui, Window};
+use egui::{Align2, CollapsingHeader, Context, Grid, RichText, ScrollArea, SidePanel, TopBottomPanel, Ui, Window};
 use walkers::{sources::Attribution, MapMemory};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+    Open,
+    SaveJson,
+    SaveXml,
+    ExportChanges,
+    OpenStyle,
+    Bookmarks,
+}
+
+pub fn menu_bar(ctx: &Context, status: Option<&str>) -> Option<MenuAction> {
+    let mut action = None;
+
+    TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+        egui::menu::bar(ui, |ui| {
+            ui.menu_button("File", |ui| {
+                if ui.button("Open…").clicked() {
+                    action = Some(MenuAction::Open);
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui.button("Save as OSM JSON…").clicked() {
+                    action = Some(MenuAction::SaveJson);
+                    ui.close_menu();
+                }
+                if ui.button("Save as OSM XML…").clicked() {
+                    action = Some(MenuAction::SaveXml);
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui.button("Export changes (.osc)…").clicked() {
+                    action = Some(MenuAction::ExportChanges);
+                    ui.close_menu();
+                }
+            });
+
+            ui.menu_button("View", |ui| {
+                if ui.button("Bookmarks").clicked() {
+                    action = Some(MenuAction::Bookmarks);
+                    ui.close_menu();
+                }
+                if ui.button("Load style…").clicked() {
+                    action = Some(MenuAction::OpenStyle);
+                    ui.close_menu();
+                }
+            });
+
+            if let Some(status) = status {
+                ui.separator();
+                ui.label(status);
+            }
+        });
+    });
+
+    action
+}
+
+// closable message for failed operations
+pub fn error(ctx: &Context, error: &mut Option<String>) {
+    let Some(message) = error.as_deref() else { return };
+    let mut open = true;
+    let mut confirmed = false;
+
+    Window::new("Error")
+        .collapsible(false)
+        .resizable(false)
+        .open(&mut open)
+        .anchor(Align2::CENTER_CENTER, [0., 0.])
+        .show(ctx, |ui| {
+            ui.label(message);
+            confirmed = ui.button("OK").clicked();
+        });
+
+    if !open || confirmed {
+        *error = None;
+    }
+}
+
 pub fn acknowledge(ui: &Ui, attribution: Attribution) {
     Window::new("Acknowledge")
         .collapsible(false)
@@ -26,6 +107,8 @@ pub fn controls(
     possible_providers: &mut dyn Iterator<Item = &Provider>,
     selected_visualization: &mut Visualization,
     scale_factor: &mut f32,
+    detail: &mut LevelOfDetail,
+    change_count: usize,
 ) {
     Window::new("Controls")
         .collapsible(false)
@@ -48,14 +131,38 @@ pub fn controls(
                     .show_ui(ui, |ui| {
                         ui.selectable_value(selected_visualization, Visualization::Default, "Default");
                         ui.selectable_value(selected_visualization, Visualization::Sidewalks, "Sidewalks");
+                        ui.selectable_value(selected_visualization, Visualization::Cycleways, "Cycleways");
+                        ui.selectable_value(selected_visualization, Visualization::Parking, "Parking");
+                        ui.selectable_value(selected_visualization, Visualization::Surface, "Surface");
                     });
 
                 ui.add(egui::Slider::new(scale_factor, 0.1..=2.0).text("Scale factor"));
             });
+
+            // zoom levels from which each kind of feature is drawn
+            ui.collapsing("Level of detail", |ui| {
+                ui.add(egui::Slider::new(&mut detail.minor_roads, 10.0..=20.0).text("Minor roads"));
+                ui.add(egui::Slider::new(&mut detail.paths, 10.0..=20.0).text("Paths"));
+                ui.add(egui::Slider::new(&mut detail.buildings, 10.0..=20.0).text("Buildings"));
+                ui.add(egui::Slider::new(&mut detail.points, 10.0..=20.0).text("Points"));
+                ui.add(egui::Slider::new(&mut detail.labels, 10.0..=20.0).text("Labels"));
+                ui.add(egui::Slider::new(&mut detail.house_numbers, 10.0..=20.0).text("House numbers"));
+                ui.add(egui::Slider::new(&mut detail.simplify_below, 10.0..=20.0).text("Simplify below"));
+            });
+
+            ui.label(format!("{change_count} changed objects"));
         });
 }
 
-pub fn zoom(ui: &Ui, map_memory: &mut MapMemory) {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZoomTarget {
+    Data,
+    Selection,
+}
+
+pub fn zoom(ui: &Ui, map_memory: &mut MapMemory, has_selection: bool) -> Option<ZoomTarget> {
+    let mut target = None;
+
     Window::new("Zoom")
         .collapsible(false)
         .resizable(false)
@@ -70,11 +177,33 @@ pub fn zoom(ui: &Ui, map_memory: &mut MapMemory) {
                 if ui.button(RichText::new("➖").heading()).clicked() {
                     let _ = map_memory.zoom_out();
                 }
+
+                if ui.button(RichText::new("⛶").heading()).on_hover_text("Zoom to data").clicked() {
+                    target = Some(ZoomTarget::Data);
+                }
+
+                if ui.add_enabled(has_selection, egui::Button::new(RichText::new("🔍").heading()))
+                    .on_hover_text("Zoom to selection")
+                    .clicked() {
+                    target = Some(ZoomTarget::Selection);
+                }
             });
         });
+
+    target
 }
 
-pub fn tags(ui: &Ui, tags: &osm_parser::Tags) {
+// returns the member or relation to select when one is clicked
+pub fn tags(
+    ui: &Ui,
+    tags: &osm_parser::Tags,
+    members: &[Member],
+    parents: &[osm_parser::Id],
+    meta: Option<&Meta>,
+    change: Option<Change>,
+) -> Option<ElementRef> {
+    let mut select = None;
+
     Window::new("Tags")
         .collapsible(true)
         .resizable(false)
@@ -88,5 +217,75 @@ pub fn tags(ui: &Ui, tags: &osm_parser::Tags) {
                     ui.end_row();
                 }
             });
+
+            if !members.is_empty() {
+                ui.separator();
+                CollapsingHeader::new(format!("Members ({})", members.len())).show(ui, |ui| {
+                    ScrollArea::vertical().max_height(200.).show(ui, |ui| {
+                        Grid::new("members").show(ui, |ui| {
+                            for member in members {
+                                let label = format!("{} {}", member.element.type_name(), member.element.id());
+                                if ui.selectable_label(false, label).clicked() {
+                                    select = Some(member.element);
+                                }
+                                ui.weak(&member.role);
+                                ui.end_row();
+                            }
+                        });
+                    });
+                });
+            }
+
+            // any relation can be selected through its members, also the ones that are not drawn
+            if !parents.is_empty() {
+                ui.separator();
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Part of");
+                    for id in parents {
+                        if ui.selectable_label(false, format!("relation {id}")).clicked() {
+                            select = Some(ElementRef::Relation(*id));
+                        }
+                    }
+                });
+            }
+
+            let version = meta.map_or("new".to_string(), |m| format!("v{}", m.version));
+            match change {
+                Some(change) => ui.weak(format!("{version}, {change:?}")),
+                None => ui.weak(version),
+            };
+        });
+
+    select
+}
+
+// returns the position to jump to when an entry is clicked
+pub fn history(ctx: &Context, history: &History) -> Option<usize> {
+    let mut jump = None;
+
+    SidePanel::right("history")
+        .resizable(true)
+        .default_width(220.)
+        .show(ctx, |ui| {
+            ui.heading("History");
+            ui.separator();
+
+            ScrollArea::vertical().show(ui, |ui| {
+                let entries = std::iter::once("Initial state").chain(history.descriptions());
+
+                for (position, description) in entries.enumerate() {
+                    let text = if position > history.position() {
+                        RichText::new(description).weak()
+                    } else {
+                        RichText::new(description)
+                    };
+
+                    if ui.selectable_label(position == history.position(), text).clicked() {
+                        jump = Some(position);
+                    }
+                }
+            });
         });
+
+    jump
 }
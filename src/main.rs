@@ -2,16 +2,20 @@ mod app;
 
 use app::MyApp;
 use eframe::egui::ViewportBuilder;
+use std::path::PathBuf;
 
 fn main() -> Result<(), eframe::Error> {
     let options = eframe::NativeOptions {
         viewport: ViewportBuilder::default().with_inner_size([1000.0, 800.0]),
         ..Default::default()
     };
-    
+
+    // data file to open on startup
+    let path = std::env::args_os().nth(1).map(PathBuf::from);
+
     eframe::run_native(
         "walkers-editor",
         options,
-        Box::new(|cc| Ok(Box::new(MyApp::new(cc.egui_ctx.clone())))),
+        Box::new(|cc| Ok(Box::new(MyApp::new(cc, path)))),
     )
 }